@@ -26,11 +26,29 @@
     html_favicon_url = "https://iot.bzh/images/defaults/favicon.ico"
 )]
 extern crate bitflags;
+// #[afb_verb] generated code uses ::afbv4 paths, including within this crate
+extern crate self as afbv4;
 
 #[cfg(test)]
 #[path = "../test/jsonc-test.rs"]
 mod test;
 
+#[cfg(test)]
+#[path = "../test/limit-test.rs"]
+mod limit_test;
+
+#[cfg(test)]
+#[path = "../test/verb-test.rs"]
+mod verb_test;
+
+#[cfg(test)]
+#[path = "../test/session-test.rs"]
+mod session_test;
+
+#[cfg(all(test, feature = "session-persist"))]
+#[path = "../test/persist-test.rs"]
+mod persist_test;
+
 // cglue is exported as private
 #[path = "../capi/cglue-mod.rs"]
 mod cglue;
@@ -50,12 +68,41 @@ pub mod utilv4;
 #[path = "./testv4-mod.rs"]
 pub mod testv4;
 
+#[path = "./asyncv4-mod.rs"]
+pub mod asyncv4;
+
+#[path = "./metricsv4-mod.rs"]
+pub mod metricsv4;
+
+#[path = "./limitv4-mod.rs"]
+pub mod limitv4;
+
+#[path = "./sessionv4-mod.rs"]
+pub mod sessionv4;
+
+#[cfg(feature = "session-persist")]
+#[path = "./persistv4-mod.rs"]
+pub mod persistv4;
+
+#[cfg(feature = "openmetrics")]
+#[path = "./openmetricsv4-mod.rs"]
+pub mod openmetricsv4;
+
 pub mod prelude {
     pub use crate::apiv4::*;
+    pub use crate::asyncv4::*;
     pub(crate) use crate::cglue;
     pub use crate::datav4::*;
     pub use crate::jsonc::*;
+    pub use crate::limitv4::*;
+    pub use crate::metricsv4::*;
+    #[cfg(feature = "openmetrics")]
+    pub use crate::openmetricsv4::*;
+    #[cfg(feature = "session-persist")]
+    pub use crate::persistv4::*;
+    pub use crate::sessionv4::*;
     pub use crate::testv4::*;
     pub use crate::utilv4::*;
+    pub use afbv4_macros::afb_verb;
     pub use std::any::Any;
 }
@@ -23,8 +23,17 @@
 
 use std::any::Any;
 use std::boxed::Box;
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashSet};
 use std::ffi::{CStr, CString};
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use std::panic::Location;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicPtr, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{
+    Arc, Condvar, Mutex, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard, TryLockError, Weak,
+};
+use std::time::{Duration, Instant};
 
 use std::fmt;
 // libafb dependencies
@@ -47,6 +56,12 @@ pub trait AfbApiSubCallControl {
 pub type RqtCallback =
     fn(rqt: &AfbRequest, args: &AfbRqtData, ctx: &AfbCtxData) -> Result<(), AfbError>;
 
+// boxed closure form of RqtCallback, captured state replaces AfbCtxData
+pub type RqtClosure =
+    Box<dyn Fn(&AfbRequest, &AfbRqtData, &AfbCtxData) -> Result<(), AfbError> + Send + Sync>;
+
+pub type RqtAsyncCallback = Box<dyn Fn(AfbRequest, AfbRqtData) -> AfbFuture + Send + Sync>;
+
 #[track_caller]
 fn rqt_default_cb(rqt: &AfbRequest, _args: &AfbRqtData, _ctx: &AfbCtxData) -> Result<(), AfbError> {
     afb_error!("afb-default-cb", "uid:{} no verb callback defined", rqt.get_verb().get_uid())
@@ -85,7 +100,8 @@ macro_rules! AfbBindingRegister {
                 },
             };
 
-            match $callback(apiv4, jconf) {
+            let init = || $callback(apiv4, jconf);
+            match afbv4::utilv4::afb_catch_panic("binding-init", init).and_then(|result| result) {
                 Ok(api) => {
                     afb_log_msg!(Notice, apiv4, "RUST api uid={} started", api.get_uid());
                     AFB_OK
@@ -129,7 +145,11 @@ macro_rules! AfbSessionRegister {
                 match request.get_session() {
                     Err(error) => Err(error),
                     Ok(any) => match any.as_any().downcast_mut::<$userdata>() {
-                        None => afb_error!("session-any-cast", "fail to restore <$userdata>"),
+                        None => afb_error!(
+                            "session-any-cast",
+                            "fail to restore <{}>",
+                            stringify!($userdata)
+                        ),
                         Some(value) => Ok(value),
                     },
                 }
@@ -143,7 +163,11 @@ macro_rules! AfbSessionRegister {
                 match request.set_session(Box::new(userdata)) {
                     Err(error) => Err(error),
                     Ok(any) => match any.as_any().downcast_mut::<$userdata>() {
-                        None => afb_error!("session-any-cast", "fail to restore <$userdata>"),
+                        None => afb_error!(
+                            "session-any-cast",
+                            "fail to restore <{}>",
+                            stringify!($userdata)
+                        ),
                         Some(value) => Ok(value),
                     },
                 }
@@ -170,7 +194,11 @@ macro_rules! AfbSessionRegister {
                 match request.get_session() {
                     Err(error) => Err(error),
                     Ok(any) => match any.as_any().downcast_mut::<$userdata>() {
-                        None => afb_error!("session-any-cast", "fail to restore <$userdata>"),
+                        None => afb_error!(
+                            "session-any-cast",
+                            "fail to restore <{}>",
+                            stringify!($userdata)
+                        ),
                         Some(value) => Ok(value),
                     },
                 }
@@ -184,7 +212,11 @@ macro_rules! AfbSessionRegister {
                 match request.set_session(Box::new(userdata)) {
                     Err(error) => Err(error),
                     Ok(any) => match any.as_any().downcast_mut::<$userdata>() {
-                        None => afb_error!("session-any-cast", "fail to restore <$userdata>"),
+                        None => afb_error!(
+                            "session-any-cast",
+                            "fail to restore <{}>",
+                            stringify!($userdata)
+                        ),
                         Some(value) => Ok(value),
                     },
                 }
@@ -198,12 +230,48 @@ macro_rules! AfbSessionRegister {
     };
 }
 
+/// Sample argument of typed verbs, `#[afb_verb]` adds it to verb samples when the verb input
+/// type implements it. `AfbDataConverter!` types implement it from their `Default` value.
+pub trait AfbVerbSample {
+    fn verb_sample() -> Option<String>;
+}
+
+// `#[afb_verb]` picks AfbSampleProbeSome when input type implements AfbVerbSample, method
+// resolution falls back to AfbSampleProbeNone (one more autoref) otherwise.
 #[doc(hidden)]
-fn add_verbs_to_group(
-    uid: &'static str,
-    info: &'static str,
-    verbs: &mut Vec<*const AfbVerb>,
-) -> JsoncObj {
+pub struct AfbSampleProbe<T: ?Sized>(std::marker::PhantomData<T>);
+
+impl<T: ?Sized> AfbSampleProbe<T> {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        AfbSampleProbe(std::marker::PhantomData)
+    }
+}
+
+#[doc(hidden)]
+pub trait AfbSampleProbeSome {
+    fn afb_sample(&self) -> Option<String>;
+}
+
+impl<T: AfbVerbSample + ?Sized> AfbSampleProbeSome for AfbSampleProbe<T> {
+    fn afb_sample(&self) -> Option<String> {
+        T::verb_sample()
+    }
+}
+
+#[doc(hidden)]
+pub trait AfbSampleProbeNone {
+    fn afb_sample(&self) -> Option<String>;
+}
+
+impl<T: ?Sized> AfbSampleProbeNone for &AfbSampleProbe<T> {
+    fn afb_sample(&self) -> Option<String> {
+        None
+    }
+}
+
+#[doc(hidden)]
+fn add_verbs_to_group(uid: &'static str, info: &'static str, verbs: &[*const AfbVerb]) -> JsoncObj {
     let jgroup = JsoncObj::new();
     if !uid.is_empty() {
         jgroup.add("uid", uid).unwrap();
@@ -213,7 +281,7 @@ fn add_verbs_to_group(
     }
     let jverbs = JsoncObj::array();
     for apiverb in verbs {
-        let verb_ref = unsafe { &mut *(*apiverb as *mut AfbVerb) };
+        let verb_ref = unsafe { &*(*apiverb) };
         let jverb = JsoncObj::new();
         jverb.add("uid", verb_ref.get_uid()).unwrap();
         jverb.add("verb", verb_ref.get_name()).unwrap();
@@ -239,33 +307,78 @@ fn add_verbs_to_group(
                 jverb.add("sample", jsamples).unwrap();
             };
         };
+
+        let input_schema = verb_ref.get_input_schema();
+        let output_schema = verb_ref.get_output_schema();
+        if input_schema.is_some() || output_schema.is_some() {
+            let jschema = JsoncObj::new();
+            if let Some(jinput) = input_schema {
+                jschema.add("input", jinput).unwrap();
+            }
+            if let Some(joutput) = output_schema {
+                jschema.add("output", joutput).unwrap();
+            }
+            jverb.add("schema", jschema).unwrap();
+        }
+        if let Some(version) = verb_ref.get_version() {
+            jverb.add("version", version).unwrap();
+        }
+        if !verb_ref.aliases.is_empty() {
+            let jaliases = JsoncObj::array();
+            for alias in &verb_ref.aliases {
+                jaliases.append(*alias).unwrap();
+            }
+            jverb.add("aliases", jaliases).unwrap();
+        }
+        if let Some((since, replacement)) = verb_ref.get_deprecated() {
+            let jdeprecated = JsoncObj::new();
+            jdeprecated.add("since", since).unwrap();
+            if !replacement.is_empty() {
+                jdeprecated.add("replacement", replacement).unwrap();
+            }
+            jverb.add("deprecated", jdeprecated).unwrap();
+        }
         jverbs.append(jverb).unwrap();
     }
     jgroup.add("verbs", jverbs).unwrap();
     jgroup
 }
 
-// Reclaim a Rust-owned Box that was previously leaked to C and free it safely.
+// group verbs with nested sub-groups as "groups" array
+fn add_group_to_info(group_ref: &AfbGroup) -> JsoncObj {
+    let jgroup = add_verbs_to_group(group_ref._uid, group_ref.info, &group_ref.verbs);
+    if !group_ref.groups.is_empty() {
+        let jsubgroups = JsoncObj::array();
+        for slot in &group_ref.groups {
+            jsubgroups.append(add_group_to_info(unsafe { &*(*slot) })).unwrap();
+        }
+        jgroup.add("groups", jsubgroups).unwrap();
+    }
+    jgroup
+}
+
+// Release libafb reference on a session context and close it.
 
-/// Reconstructs and frees an `AfbRqtSessionWrap` that was leaked to C.
+/// Closes and releases an `AfbRqtSessionWrap` handed to C.
 ///
 /// This function is meant to be used as a C callback/deleter. It restores the
-/// original `Box<AfbRqtSessionWrap>` from the raw pointer passed through FFI,
-/// calls a graceful shutdown hook, then drops the Box so Rust runs `Drop`.
+/// `Arc<AfbRqtSessionWrap>` reference owned by libafb from the raw pointer passed
+/// through FFI, calls a graceful shutdown hook, then drops this reference.
 ///
 /// # Safety
 /// - `context` **must** be a non-null pointer originally produced by
-///   `Box::leak::<AfbRqtSessionWrap>(...)`.
+///   `Arc::into_raw::<AfbRqtSessionWrap>(...)`.
 /// - Passing any other pointer (wrong type, already-freed, or stack memory)
 ///   is **undefined behavior**.
 /// - This function must only be called **once** for a given `context`.
 ///
 /// # Behavior
 /// - If `context` is null, the function returns immediately (no-op).
-/// - Calls `AfbRqtSessionWrap::inner.closing()` to allow the session to flush/close
-///   resources before destruction.
-/// - Rebuilds the `Box` with `Box::from_raw` and lets it drop at end of scope,
-///   which runs the type’s `Drop` implementation.
+/// - Calls `AfbRqtSessionWrap::closing()` to let `AfbRqtSession::closing()` flush/close
+///   resources before destruction and release the binding-wide shared session, typed
+///   session values are dropped.
+/// - Rebuilds the `Arc` with `Arc::from_raw` and drops it, the last reference
+///   runs the type’s `Drop` implementation.
 #[no_mangle]
 pub unsafe extern "C" fn free_session_cb(context: *mut std::ffi::c_void) {
     // Defensive: ignore null pointers coming from C.
@@ -274,20 +387,39 @@ pub unsafe extern "C" fn free_session_cb(context: *mut std::ffi::c_void) {
         return;
     }
 
-    // SAFETY: Callers promise that `context` came from `Box::leak(AfbRqtSessionWrap)`.
-    // We can therefore reborrow it temporarily to run the pre-drop hook.
-    let wrap = &mut *(context as *mut AfbRqtSessionWrap);
+    // SAFETY: Callers promise that `context` came from `Arc::into_raw(AfbRqtSessionWrap)`.
+    // This releases libafb reference, requests still working on the context hold their own.
+    let wrap = Arc::from_raw(context as *const AfbRqtSessionWrap);
 
     // Allow the session to gracefully close before we drop it.
     // This is useful to flush logs, release handles, etc.
-    wrap.inner.closing();
-
-    // SAFETY: Restore ownership to Rust so that `Drop` runs exactly once.
-    // After this point, `context` must not be used again on the C side.
-    let cbox: Box<AfbRqtSessionWrap> = Box::from_raw(context as *mut AfbRqtSessionWrap);
+    // User closing hooks and value destructors must not unwind into libafb.
+    let closing = afb_catch_panic("session-closing", || {
+        wrap.closing();
+        // Dropping the last `Arc` runs `Drop` for `AfbRqtSessionWrap` and its fields.
+        drop(wrap);
+    });
+    if let Err(error) = closing {
+        afb_log_msg!(Error, None, &error);
+    }
+}
 
-    // Dropping the `Box` will run `Drop` for `AfbRqtSessionWrap` and its fields.
-    drop(cbox);
+// builtin verbs run no user code, a panic in them (e.g. jsonc failure) is still caught and
+// replied as any verb panic. Request reference is taken first: callback may release its own.
+pub(crate) unsafe fn afb_builtin_catch_panic(
+    uid: &str,
+    rqtv4: cglue::afb_req_t,
+    callback: impl FnOnce(),
+) {
+    let rqtv4 = cglue::afb_req_addref(rqtv4);
+    match afb_catch_panic(uid, callback) {
+        Ok(()) => cglue::afb_req_unref(rqtv4),
+        Err(error) => {
+            let request = AfbRequest::from_raw(rqtv4);
+            afb_log_msg!(Critical, &request, &error);
+            request.reply(error, -100);
+        },
+    }
 }
 
 /// # Safety
@@ -295,9 +427,13 @@ pub unsafe extern "C" fn free_session_cb(context: *mut std::ffi::c_void) {
 #[no_mangle]
 pub unsafe extern "C" fn api_info_cb(
     rqtv4: cglue::afb_req_t,
-    _argc: u32,
-    _args: *const cglue::afb_data_t,
+    argc: u32,
+    args: *const cglue::afb_data_t,
 ) {
+    afb_builtin_catch_panic("builtin-info", rqtv4, || api_info(rqtv4, argc, args));
+}
+
+unsafe fn api_info(rqtv4: cglue::afb_req_t, _argc: u32, _args: *const cglue::afb_data_t) {
     let api_ref = unsafe {
         let vcbdata = cglue::afb_req_get_vcbdata(rqtv4);
         &mut *(vcbdata as *mut AfbApi)
@@ -315,13 +451,10 @@ pub unsafe extern "C" fn api_info_cb(
 
     // create groups array to host verbs
     let jgroups = JsoncObj::array();
-    jgroups.append(add_verbs_to_group("", "", &mut api_ref.verbs)).unwrap();
+    jgroups.append(add_verbs_to_group("", "", &api_ref.get_verb_slots())).unwrap();
 
-    for slot in &api_ref.groups {
-        let group_ref = unsafe { &mut *(*slot as *mut AfbGroup) };
-        jgroups
-            .append(add_verbs_to_group(group_ref._uid, group_ref.info, &mut group_ref.verbs))
-            .unwrap();
+    for slot in api_ref.get_api_group_slots() {
+        jgroups.append(add_group_to_info(unsafe { &*slot })).unwrap();
     }
 
     jinfo.add("groups", jgroups).unwrap();
@@ -330,15 +463,409 @@ pub unsafe extern "C" fn api_info_cb(
     request.reply(jinfo, 0);
 }
 
+// permissions are flatten as an OR of AND requirement lists (OpenAPI security layout)
+type SecurityAlt = Vec<Vec<(&'static str, String)>>;
+
+fn security_and(left: &SecurityAlt, right: &SecurityAlt) -> SecurityAlt {
+    let mut result = Vec::new();
+    for lhs in left {
+        for rhs in right {
+            result.push(lhs.iter().chain(rhs.iter()).cloned().collect());
+        }
+    }
+    result
+}
+
+fn permission_to_security(permission: &AfbPermission) -> SecurityAlt {
+    match permission {
+        AfbPermission::Loa(loa) => vec![vec![("afb-loa", format!("loa:{}", loa))]],
+        AfbPermission::Require(value) => vec![vec![("afb-permission", value.to_string())]],
+        AfbPermission::AnyOf(list) => list.iter().flat_map(permission_to_security).collect(),
+        AfbPermission::AllOf(list) => list
+            .iter()
+            .fold(vec![Vec::new()], |acc, slot| security_and(&acc, &permission_to_security(slot))),
+        AfbPermission::Inner(inner) => permission_to_security(inner),
+        AfbPermission::None() => vec![Vec::new()],
+    }
+}
+
+fn security_to_jsonc(security: &SecurityAlt) -> Result<Option<JsoncObj>, AfbError> {
+    if security.iter().all(|requirement| requirement.is_empty()) {
+        return Ok(None);
+    }
+    let jsecurity = JsoncObj::array();
+    for requirement in security {
+        let jrequirement = JsoncObj::new();
+        for (scheme, role) in requirement {
+            let jroles = match jrequirement.optional::<JsoncObj>(scheme)? {
+                Some(jroles) => jroles,
+                None => {
+                    jrequirement.add(scheme, JsoncObj::array())?;
+                    jrequirement.get::<JsoncObj>(scheme)?
+                },
+            };
+            jroles.append(role)?;
+        }
+        jsecurity.append(jrequirement)?;
+    }
+    Ok(Some(jsecurity))
+}
+
+// group verbs tagged with group uid, sub-groups add their own permission to parent one
+fn openapi_add_group(
+    api: &AfbApi,
+    jpaths: &JsoncObj,
+    jtags: &JsoncObj,
+    security: &SecurityAlt,
+    group_ref: &AfbGroup,
+) -> Result<(), AfbError> {
+    let group_security = security_and(security, &permission_to_security(group_ref.permission));
+    openapi_add_verbs(api, jpaths, group_ref._uid, &group_security, &group_ref.verbs)?;
+
+    let jtag = JsoncObj::new();
+    jtag.add("name", group_ref._uid)?;
+    if !group_ref.info.is_empty() {
+        jtag.add("description", group_ref.info)?;
+    }
+    jtags.append(jtag)?;
+
+    for slot in &group_ref.groups {
+        openapi_add_group(api, jpaths, jtags, &group_security, unsafe { &*(*slot) })?;
+    }
+    Ok(())
+}
+
+fn openapi_add_verbs(
+    api: &AfbApi,
+    jpaths: &JsoncObj,
+    tag: &'static str,
+    security: &SecurityAlt,
+    verbs: &[*const AfbVerb],
+) -> Result<(), AfbError> {
+    for slot in verbs {
+        let verb_ref = unsafe { &*(*slot) };
+        let jpost = JsoncObj::new();
+        jpost.add("operationId", verb_ref.get_uid())?;
+        jpost.add("summary", verb_ref.get_info())?;
+        if verb_ref.get_deprecated().is_some() {
+            jpost.add("deprecated", true)?;
+        }
+        if let Some(usage) = verb_ref.get_usage() {
+            jpost.add("description", usage)?;
+        }
+        if let Some(version) = verb_ref.version {
+            jpost.add("x-afb-version", version)?;
+        }
+        if !tag.is_empty() {
+            let jtags = JsoncObj::array();
+            jtags.append(tag)?;
+            jpost.add("tags", jtags)?;
+        }
+
+        let jrequest = JsoncObj::new();
+        jrequest.add("schema", verb_ref.get_input_schema().unwrap_or_default())?;
+        let jsamples = verb_ref.get_samples();
+        let count = jsamples.count()?;
+        if count > 0 {
+            let jexamples = JsoncObj::new();
+            for idx in 0..count {
+                let jexample = JsoncObj::new();
+                jexample.add("value", jsamples.index::<JsoncObj>(idx)?)?;
+                jexamples.add(&format!("sample-{}", idx), jexample)?;
+            }
+            jrequest.add("examples", jexamples)?;
+        }
+        let jcontent = JsoncObj::new();
+        jcontent.add("application/json", jrequest)?;
+        let jbody = JsoncObj::new();
+        jbody.add("content", jcontent)?;
+        jpost.add("requestBody", jbody)?;
+
+        let jresponse = JsoncObj::new();
+        jresponse.add("schema", verb_ref.get_output_schema().unwrap_or_default())?;
+        let jcontent = JsoncObj::new();
+        jcontent.add("application/json", jresponse)?;
+        let jsuccess = JsoncObj::new();
+        jsuccess.add("description", "verb response")?;
+        jsuccess.add("content", jcontent)?;
+        let jresponses = JsoncObj::new();
+        jresponses.add("200", jsuccess)?;
+        jpost.add("responses", jresponses)?;
+
+        let verb_security = security_and(security, &permission_to_security(verb_ref.permission));
+        if let Some(jsecurity) = security_to_jsonc(&verb_security)? {
+            jpost.add("security", jsecurity)?;
+        }
+
+        let jpath = JsoncObj::new();
+        jpath.add("post", jpost)?;
+        jpaths.add(&format!("/api/{}/{}", api.get_name(), verb_ref.get_name()), jpath)?;
+
+        // aliases reference verb path item, '/' is escaped as '~1' in json pointers
+        for alias in &verb_ref.aliases {
+            let jalias = JsoncObj::new();
+            let pointer = format!("/api/{}/{}", api.get_name(), verb_ref.get_name())
+                .replace('~', "~0")
+                .replace('/', "~1");
+            jalias.add("$ref", &format!("#/paths/{}", pointer))?;
+            jpaths.add(&format!("/api/{}/{}", api.get_name(), alias), jalias)?;
+        }
+    }
+    Ok(())
+}
+
+// unversioned verb operation, the 'version' field selects one of the x-afb-version operations
+fn openapi_add_versions(api: &AfbApi, jpaths: &JsoncObj) -> Result<(), AfbError> {
+    let dispatchers = api.dispatchers.read().unwrap_or_else(PoisonError::into_inner);
+    for dispatcher in dispatchers.iter() {
+        let versions = dispatcher.get_versions();
+        let jenum = JsoncObj::array();
+        for version in &versions {
+            jenum.append(*version)?;
+        }
+        let jversion = JsoncObj::new();
+        jversion.add("type", "string")?;
+        jversion.add("enum", jenum)?;
+        if let Some(version) = versions.last() {
+            jversion.add("default", *version)?;
+        }
+        let jproperties = JsoncObj::new();
+        jproperties.add("version", jversion)?;
+        let jschema = JsoncObj::new();
+        jschema.add("type", "object")?;
+        jschema.add("properties", jproperties)?;
+
+        let jpost = JsoncObj::new();
+        jpost.add("operationId", dispatcher.name)?;
+        jpost.add("summary", "versioned verb, select with 'version' field")?;
+        jpost.add("description", &format!("versions: {}", versions.join(", ")))?;
+
+        let jrequest = JsoncObj::new();
+        jrequest.add("schema", jschema)?;
+        let jcontent = JsoncObj::new();
+        jcontent.add("application/json", jrequest)?;
+        let jbody = JsoncObj::new();
+        jbody.add("content", jcontent)?;
+        jpost.add("requestBody", jbody)?;
+
+        let jsuccess = JsoncObj::new();
+        jsuccess.add("description", "selected version response")?;
+        let jresponses = JsoncObj::new();
+        jresponses.add("200", jsuccess)?;
+        jpost.add("responses", jresponses)?;
+
+        let jpath = JsoncObj::new();
+        jpath.add("post", jpost)?;
+        jpaths.add(&format!("/api/{}/{}", api.get_name(), dispatcher.name), jpath)?;
+    }
+    Ok(())
+}
+
+fn asyncapi_add_events(
+    api: &AfbApi,
+    jchannels: &JsoncObj,
+    joperations: &JsoncObj,
+    events: &[*const AfbEvent],
+) -> Result<(), AfbError> {
+    for slot in events {
+        let event_ref = unsafe { &*(*slot) };
+        let uid = event_ref.get_uid();
+
+        let jmessage = JsoncObj::new();
+        jmessage.add("name", uid)?;
+        jmessage.add("contentType", "application/json")?;
+        jmessage.add("payload", JsoncObj::new())?;
+        let jmessages = JsoncObj::new();
+        jmessages.add(uid, jmessage)?;
+
+        let jchannel = JsoncObj::new();
+        jchannel.add("address", &format!("{}/{}", api.get_name(), uid))?;
+        if !event_ref.get_info().is_empty() {
+            jchannel.add("description", event_ref.get_info())?;
+        }
+        jchannel.add("messages", jmessages)?;
+        jchannels.add(uid, jchannel)?;
+
+        let jref = JsoncObj::new();
+        jref.add("$ref", &format!("#/channels/{}", uid))?;
+        let joperation = JsoncObj::new();
+        joperation.add("action", "receive")?;
+        joperation.add("channel", jref)?;
+        joperations.add(&format!("receive-{}", uid), joperation)?;
+    }
+    Ok(())
+}
+
+/// # Safety
+/// `rqtv4` is provided by libafb; `_args` must point to an array of `argc` items.
+#[no_mangle]
+pub unsafe extern "C" fn api_apidoc_cb(
+    rqtv4: cglue::afb_req_t,
+    argc: u32,
+    args: *const cglue::afb_data_t,
+) {
+    afb_builtin_catch_panic("builtin-apidoc", rqtv4, || api_apidoc(rqtv4, argc, args));
+}
+
+unsafe fn api_apidoc(rqtv4: cglue::afb_req_t, _argc: u32, _args: *const cglue::afb_data_t) {
+    let api_ref = unsafe {
+        let vcbdata = cglue::afb_req_get_vcbdata(rqtv4);
+        &*(vcbdata as *const AfbApi)
+    };
+
+    let request = AfbRequest::from_raw(rqtv4);
+    let apidoc = || -> Result<JsoncObj, AfbError> {
+        let jdoc = JsoncObj::new();
+        jdoc.add("openapi", api_ref.get_openapi()?)?;
+        jdoc.add("asyncapi", api_ref.get_asyncapi()?)?;
+        Ok(jdoc)
+    };
+    match apidoc() {
+        Ok(jdoc) => request.reply(jdoc, 0),
+        Err(error) => request.reply(error, -100),
+    }
+}
+
+/// # Safety
+/// `rqtv4` is provided by libafb; `args` must point to an array of `argc` items.
+#[no_mangle]
+pub unsafe extern "C" fn api_stats_cb(
+    rqtv4: cglue::afb_req_t,
+    argc: u32,
+    args: *const cglue::afb_data_t,
+) {
+    afb_builtin_catch_panic("builtin-stats", rqtv4, || api_stats(rqtv4, argc, args));
+}
+
+unsafe fn api_stats(rqtv4: cglue::afb_req_t, argc: u32, args: *const cglue::afb_data_t) {
+    let api_ref = unsafe {
+        let vcbdata = cglue::afb_req_get_vcbdata(rqtv4);
+        &*(vcbdata as *const AfbApi)
+    };
+
+    let request = AfbRequest::from_raw(rqtv4);
+    let stats = || -> Result<JsoncObj, AfbError> {
+        let jstats = api_ref.get_stats()?;
+        // optional {"reset":true} clears counters once returned
+        let args =
+            AfbRqtData::new(unsafe { std::slice::from_raw_parts(args, argc as usize) }, argc, 0);
+        if let Ok(jquery) = args.get::<JsoncObj>(0) {
+            if let Ok(true) = jquery.get::<bool>("reset") {
+                api_ref.reset_stats();
+            }
+        }
+        Ok(jstats)
+    };
+    match stats() {
+        Ok(jstats) => request.reply(jstats, 0),
+        Err(error) => request.reply(error, -100),
+    }
+}
+
+// current masks of api, groups, verbs and event handlers
+fn verbosity_to_jsonc(api_ref: &AfbApi) -> Result<JsoncObj, AfbError> {
+    let jverbs = JsoncObj::new();
+    for verb_ref in api_ref.get_all_verbs() {
+        jverbs.add(verb_ref.get_name(), verb_ref.verbosity.load(Ordering::Relaxed))?;
+    }
+    let jgroups = JsoncObj::new();
+    let jhandlers = JsoncObj::new();
+    for slot in &api_ref.evthandlers {
+        let handler_ref = unsafe { &*(*slot) };
+        jhandlers.add(handler_ref.get_uid(), handler_ref.get_verbosity())?;
+    }
+    for group_ref in api_ref.get_all_groups() {
+        jgroups.add(group_ref.get_uid(), group_ref.get_verbosity())?;
+        for slot in &group_ref.evthandlers {
+            let handler_ref = unsafe { &*(*slot) };
+            jhandlers.add(handler_ref.get_uid(), handler_ref.get_verbosity())?;
+        }
+    }
+    let jverbosity = JsoncObj::new();
+    jverbosity.add("api", api_ref.verbosity.load(Ordering::Relaxed))?;
+    jverbosity.add("groups", jgroups)?;
+    jverbosity.add("verbs", jverbs)?;
+    jverbosity.add("handlers", jhandlers)?;
+    Ok(jverbosity)
+}
+
+/// # Safety
+/// `rqtv4` is provided by libafb; `args` must point to an array of `argc` items.
+#[no_mangle]
+pub unsafe extern "C" fn api_verbosity_cb(
+    rqtv4: cglue::afb_req_t,
+    argc: u32,
+    args: *const cglue::afb_data_t,
+) {
+    afb_builtin_catch_panic("builtin-verbosity", rqtv4, || api_verbosity(rqtv4, argc, args));
+}
+
+unsafe fn api_verbosity(rqtv4: cglue::afb_req_t, argc: u32, args: *const cglue::afb_data_t) {
+    let api_ref = unsafe {
+        let vcbdata = cglue::afb_req_get_vcbdata(rqtv4);
+        &*(vcbdata as *const AfbApi)
+    };
+
+    let request = AfbRequest::from_raw(rqtv4);
+    // {"level":n} targets the api, add "group", "verb" or "handler" uid to narrow it down
+    let verbosity = || -> Result<JsoncObj, AfbError> {
+        let args =
+            AfbRqtData::new(unsafe { std::slice::from_raw_parts(args, argc as usize) }, argc, 0);
+        let jquery = match args.get::<JsoncObj>(0) {
+            Ok(jquery) => jquery,
+            Err(_) => return verbosity_to_jsonc(api_ref),
+        };
+        let level = match jquery.optional::<i32>("level")? {
+            Some(level) => level,
+            None => return verbosity_to_jsonc(api_ref),
+        };
+
+        if let Some(uid) = jquery.optional::<String>("verb")? {
+            match api_ref.get_all_verbs().iter().find(|verb| verb._uid == uid || verb.name == uid) {
+                Some(verb_ref) => verb_ref.update_verbosity(level)?,
+                None => return afb_error!("verbosity-unknown", "unknown verb:{}", uid),
+            };
+        } else if let Some(uid) = jquery.optional::<String>("group")? {
+            match api_ref.get_all_groups().iter().find(|group| group._uid == uid) {
+                Some(group_ref) => group_ref.update_verbosity(level)?,
+                None => return afb_error!("verbosity-unknown", "unknown group:{}", uid),
+            };
+        } else if let Some(uid) = jquery.optional::<String>("handler")? {
+            let handler = api_ref
+                .evthandlers
+                .iter()
+                .chain(api_ref.get_all_groups().iter().flat_map(|group| group.evthandlers.iter()))
+                .map(|slot| unsafe { &*(*slot) })
+                .find(|handler| handler._uid == uid);
+            match handler {
+                Some(handler_ref) => handler_ref.update_verbosity(level)?,
+                None => return afb_error!("verbosity-unknown", "unknown handler:{}", uid),
+            };
+        } else {
+            api_ref.update_verbosity(level)?;
+        }
+        afb_log_msg!(Notice, &request, "verbosity updated query:{}", jquery);
+        verbosity_to_jsonc(api_ref)
+    };
+    match verbosity() {
+        Ok(jverbosity) => request.reply(jverbosity, 0),
+        Err(error) => request.reply(error, -4),
+    }
+}
+
 /// # Safety
 /// - `rqtv4` is provided by libafb and must be a valid request handle.
 /// - `_args` must point to an array of `_argc` items (or be null when `_argc == 0`).
 #[no_mangle]
 pub unsafe extern "C" fn api_ping_cb(
     rqtv4: cglue::afb_req_t,
-    _argc: u32,
-    _args: *const cglue::afb_data_t,
+    argc: u32,
+    args: *const cglue::afb_data_t,
 ) {
+    afb_builtin_catch_panic("builtin-ping", rqtv4, || api_ping(rqtv4, argc, args));
+}
+
+unsafe fn api_ping(rqtv4: cglue::afb_req_t, _argc: u32, _args: *const cglue::afb_data_t) {
     // Increment the per-process ping counter.
     static mut COUNTER: u32 = 0;
     unsafe { COUNTER += 1 };
@@ -352,7 +879,23 @@ pub unsafe extern "C" fn api_ping_cb(
     request.reply(jpong, 0);
 }
 
-pub trait AfbApiControls {
+/// Any conversion implemented for every api userdata, `AfbApiControls::as_any` does not need to
+/// be written by hand anymore.
+pub trait AfbApiControlsAny {
+    fn as_any_ref(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Any> AfbApiControlsAny for T {
+    fn as_any_ref(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+pub trait AfbApiControls: AfbApiControlsAny {
     #[track_caller]
     fn config(&mut self, api: &AfbApi, config: JsoncObj) -> Result<(), AfbError> {
         afb_log_msg!(
@@ -387,7 +930,111 @@ pub trait AfbApiControls {
         code
     }
 
-    fn as_any(&mut self) -> &mut dyn Any;
+    fn as_any(&mut self) -> &mut dyn Any {
+        self.as_any_mut()
+    }
+}
+
+pub enum AfbInterceptStatus {
+    /// run next interceptor then verb callback
+    Continue,
+    /// interceptor replied to the request, verb callback is skipped
+    Replied,
+}
+
+/// Common logic run around every verb of an api or group (auth, tracing, audit, ...).
+/// Api interceptors run before group ones, `after` runs for every interceptor whose
+/// `before` was called, in reverse order, when the request is replied.
+/// Interceptors are shared by every binder thread running a request.
+pub trait AfbInterceptor: Send + Sync {
+    /// An error short-circuits the verb and is replied with its status when negative,
+    /// -100 otherwise.
+    fn before(
+        &self,
+        _request: &AfbRequest,
+        _args: &AfbRqtData,
+    ) -> Result<AfbInterceptStatus, AfbError> {
+        Ok(AfbInterceptStatus::Continue)
+    }
+
+    fn after(&self, _request: &AfbRequest, _status: i32, _reply: &AfbParams) {}
+}
+
+thread_local! {
+    // api userdata guards held by current thread: api address and exclusive flag
+    static AFB_API_DATA_HELD: RefCell<Vec<(usize, bool)>> = const { RefCell::new(Vec::new()) };
+}
+
+// one api userdata guard of current thread, forgotten on drop
+struct AfbApiDataHeld {
+    api: usize,
+    exclusive: bool,
+}
+
+impl AfbApiDataHeld {
+    // None when current thread holds no guard on `api`, else whether one is exclusive
+    fn lookup(api: usize) -> Option<bool> {
+        AFB_API_DATA_HELD.with(|held| {
+            held.borrow()
+                .iter()
+                .filter(|(held, _)| *held == api)
+                .map(|(_, exclusive)| *exclusive)
+                .reduce(|first, second| first || second)
+        })
+    }
+
+    fn push(api: usize, exclusive: bool) -> Self {
+        AFB_API_DATA_HELD.with(|held| held.borrow_mut().push((api, exclusive)));
+        AfbApiDataHeld { api, exclusive }
+    }
+}
+
+impl Drop for AfbApiDataHeld {
+    fn drop(&mut self) {
+        AFB_API_DATA_HELD.with(|held| {
+            let mut held = held.borrow_mut();
+            if let Some(idx) = held.iter().rposition(|held| *held == (self.api, self.exclusive)) {
+                held.remove(idx);
+            }
+        });
+    }
+}
+
+/// Shared access to api userdata, see `AfbApi::api_data`.
+pub struct AfbApiDataRef<'a, T: ?Sized> {
+    data: &'a T,
+    // None when current thread already holds a shared guard on the same api
+    _lock: Option<RwLockReadGuard<'a, ()>>,
+    _held: AfbApiDataHeld,
+}
+
+impl<T: ?Sized> Deref for AfbApiDataRef<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.data
+    }
+}
+
+/// Exclusive access to api userdata, see `AfbApi::api_data_mut`.
+pub struct AfbApiDataMut<'a, T: ?Sized> {
+    data: &'a mut T,
+    _lock: RwLockWriteGuard<'a, ()>,
+    _held: AfbApiDataHeld,
+}
+
+impl<T: ?Sized> Deref for AfbApiDataMut<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.data
+    }
+}
+
+impl<T: ?Sized> DerefMut for AfbApiDataMut<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.data
+    }
 }
 
 #[doc(hidden)]
@@ -431,6 +1078,23 @@ pub unsafe extern "C" fn api_controls_cb(
     ctlid: cglue::afb_ctlid_t,
     ctlarg_v4: cglue::afb_ctlarg_t,
     apictx: *mut std::ffi::c_void,
+) -> i32 {
+    let api_ref = &*(apictx as *mut AfbApi);
+    let _scope = AfbExitScope::enter(apiv4);
+    match afb_catch_panic(api_ref._uid, || api_controls_dispatch(apiv4, ctlid, ctlarg_v4, apictx)) {
+        Ok(status) => status,
+        Err(error) => {
+            afb_log_msg!(Critical, apiv4, &error);
+            AFB_FAIL
+        },
+    }
+}
+
+unsafe fn api_controls_dispatch(
+    apiv4: cglue::afb_api_t,
+    ctlid: cglue::afb_ctlid_t,
+    ctlarg_v4: cglue::afb_ctlarg_t,
+    apictx: *mut std::ffi::c_void,
 ) -> i32 {
     // extract config rust object from C void* ctrlbox
     //let ctlid = ctlid_v4 as cglue::afb_ctlid_t;
@@ -441,8 +1105,12 @@ pub unsafe extern "C" fn api_controls_cb(
         cglue::afb_ctlid_afb_ctlid_Pre_Init => {
             // reference native afb apiv4 within rust api object
             api_ref.set_apiv4(apiv4);
-            let mut status = match api_ref.ctrlbox {
-                Some(ctrlbox) => {
+            let mut status = match api_ref.lock_controls() {
+                Err(error) => {
+                    afb_log_msg!(Critical, apiv4, &error);
+                    AFB_FAIL
+                },
+                Ok(Some(mut ctrlbox)) => {
                     let jconfig = match binding_parse_config(apiv4, ctlarg) {
                         Ok(value) => value,
                         Err(error) => {
@@ -460,7 +1128,7 @@ pub unsafe extern "C" fn api_controls_cb(
                         },
                     };
 
-                    match unsafe { (*ctrlbox).config(api_ref, jconfig) } {
+                    match ctrlbox.config(api_ref, jconfig) {
                         Err(error) => {
                             let dbg = error.get_dbg();
                             afb_log_raw!(
@@ -477,7 +1145,7 @@ pub unsafe extern "C" fn api_controls_cb(
                         Ok(()) => AFB_OK,
                     }
                 },
-                None => 0,
+                Ok(None) => 0,
             };
 
             if status >= 0 {
@@ -503,13 +1171,11 @@ pub unsafe extern "C" fn api_controls_cb(
 
             // pre_init config ok, let's loop on api verb array registration
             if status >= 0 {
-                for slot in &api_ref.verbs {
-                    let verb_ref = unsafe { &mut *(*slot as *mut AfbVerb) };
+                for slot in api_ref.get_verb_slots() {
+                    let verb_ref = unsafe { &*slot };
 
                     // use api verbosity is higger than verb one
-                    if api_ref.verbosity > verb_ref.verbosity {
-                        verb_ref.verbosity = api_ref.verbosity;
-                    }
+                    verb_ref.inherit_verbosity(api_ref.verbosity.load(Ordering::Relaxed));
 
                     let rc = unsafe { verb_ref.register(apiv4, api_auth) };
                     if rc < 0 {
@@ -527,8 +1193,8 @@ pub unsafe extern "C" fn api_controls_cb(
             }
 
             if status >= 0 {
-                for slot in &api_ref.groups {
-                    let group_ref = unsafe { &mut *(*slot as *mut AfbGroup) };
+                for slot in api_ref.get_api_group_slots() {
+                    let group_ref = unsafe { &*slot };
                     status = unsafe { group_ref.register(apiv4, api_auth) };
                     if status < 0 {
                         afb_log_msg!(
@@ -542,13 +1208,15 @@ pub unsafe extern "C" fn api_controls_cb(
                 }
             };
 
+            if status >= 0 {
+                status = register_versions(api_ref, apiv4, &api_ref.get_all_verbs());
+            }
+
             if status >= 0 {
                 for slot in &api_ref.evthandlers {
                     let event_ref = unsafe { &mut *(*slot as *mut AfbEvtHandler) };
                     // use api verbosity is higger than verb one
-                    if api_ref.verbosity > event_ref.verbosity {
-                        event_ref.verbosity = api_ref.verbosity;
-                    }
+                    event_ref.inherit_verbosity(api_ref.verbosity.load(Ordering::Relaxed));
                     status = event_ref.register(apiv4);
                     if status < 0 {
                         afb_log_msg!(
@@ -567,8 +1235,9 @@ pub unsafe extern "C" fn api_controls_cb(
                 for slot in &api_ref.events {
                     let event_ref = unsafe { &mut *(*slot as *mut AfbEvent) };
                     // use api verbosity is higger than verb one
-                    if api_ref.verbosity > event_ref.verbosity {
-                        event_ref.verbosity = api_ref.verbosity;
+                    let api_verbosity = api_ref.verbosity.load(Ordering::Relaxed);
+                    if api_verbosity > event_ref.verbosity {
+                        event_ref.verbosity = api_verbosity;
                     }
                     status = event_ref.register(apiv4);
                     if status < 0 {
@@ -602,17 +1271,17 @@ pub unsafe extern "C" fn api_controls_cb(
                 }
             };
 
-            // add verb info
-            if status >= 0 && api_ref.do_info {
-                let verb_name = CString::new("info").unwrap();
+            // add verb apidoc
+            if status >= 0 && api_ref.do_apidoc {
+                let verb_name = CString::new("apidoc").unwrap();
                 let verb_info =
-                    CString::new("libafb automatic introspection of api verbs").unwrap();
+                    CString::new("libafb automatic openapi/asyncapi documentation").unwrap();
                 status = unsafe {
                     cglue::afb_api_add_verb(
                         apiv4,
                         verb_name.as_ptr(),
                         verb_info.as_ptr(),
-                        Some(api_info_cb),
+                        Some(api_apidoc_cb),
                         api_ref as *const _ as *mut std::ffi::c_void,
                         AFB_NO_AUTH, // const struct afb_auth *auth,
                         0,
@@ -620,34 +1289,119 @@ pub unsafe extern "C" fn api_controls_cb(
                     )
                 }
             };
-            if status >= 0 && api_ref.do_seal {
-                unsafe { cglue::afb_api_seal(apiv4) }
-            }
-            status
-        },
 
-        cglue::afb_ctlid_afb_ctlid_Init => match api_ref.ctrlbox {
-            Some(ctrlbox) => match unsafe { (*ctrlbox).start(api_ref) } {
-                Ok(()) => AFB_OK,
-                Err(error) => {
-                    let dbg = error.get_dbg();
-                    afb_log_raw!(
-                        Critical,
+            // add verb stats
+            if let Some(permission) = api_ref.do_stats.filter(|_| status >= 0) {
+                let verb_name = CString::new("stats").unwrap();
+                let verb_info = CString::new("libafb automatic per verb request metrics").unwrap();
+                status = unsafe {
+                    cglue::afb_api_add_verb(
                         apiv4,
-                        "binding start fail:{} file: {}:{}:{}",
-                        error.get_info(),
-                        dbg.file,
-                        dbg.line,
-                        dbg.column
-                    );
-                    AFB_FAIL
+                        verb_name.as_ptr(),
+                        verb_info.as_ptr(),
+                        Some(api_stats_cb),
+                        api_ref as *const _ as *mut std::ffi::c_void,
+                        AfbPermisionV4::new(permission, api_auth),
+                        0,
+                        0,
+                    )
+                }
+            };
+
+            // add verb verbosity
+            if let Some(permission) = api_ref.do_verbosity.filter(|_| status >= 0) {
+                let verb_name = CString::new("verbosity").unwrap();
+                let verb_info = CString::new("libafb runtime verbosity control").unwrap();
+                status = unsafe {
+                    cglue::afb_api_add_verb(
+                        apiv4,
+                        verb_name.as_ptr(),
+                        verb_info.as_ptr(),
+                        Some(api_verbosity_cb),
+                        api_ref as *const _ as *mut std::ffi::c_void,
+                        AfbPermisionV4::new(permission, api_auth),
+                        0,
+                        0,
+                    )
+                }
+            };
+
+            // add verb metrics
+            #[cfg(feature = "openmetrics")]
+            if status >= 0 && api_ref.do_metrics {
+                let verb_name = CString::new("metrics").unwrap();
+                let verb_info = CString::new("libafb automatic openmetrics text exporter").unwrap();
+                status = unsafe {
+                    cglue::afb_api_add_verb(
+                        apiv4,
+                        verb_name.as_ptr(),
+                        verb_info.as_ptr(),
+                        Some(api_metrics_cb),
+                        api_ref as *const _ as *mut std::ffi::c_void,
+                        AFB_NO_AUTH, // const struct afb_auth *auth,
+                        0,
+                        0,
+                    )
+                }
+            };
+
+            // add verb info
+            if status >= 0 && api_ref.do_info {
+                let verb_name = CString::new("info").unwrap();
+                let verb_info =
+                    CString::new("libafb automatic introspection of api verbs").unwrap();
+                status = unsafe {
+                    cglue::afb_api_add_verb(
+                        apiv4,
+                        verb_name.as_ptr(),
+                        verb_info.as_ptr(),
+                        Some(api_info_cb),
+                        api_ref as *const _ as *mut std::ffi::c_void,
+                        AFB_NO_AUTH, // const struct afb_auth *auth,
+                        0,
+                        0,
+                    )
+                }
+            };
+            if status >= 0 && api_ref.do_seal {
+                unsafe { cglue::afb_api_seal(apiv4) }
+            }
+            if status >= 0 {
+                AFB_APIS_RUNNING.fetch_add(1, Ordering::AcqRel);
+            }
+            status
+        },
+
+        cglue::afb_ctlid_afb_ctlid_Init => match api_ref.lock_controls() {
+            Err(error) => {
+                afb_log_msg!(Critical, apiv4, &error);
+                AFB_FAIL
+            },
+            Ok(Some(mut ctrlbox)) => match ctrlbox.start(api_ref) {
+                Ok(()) => AFB_OK,
+                Err(error) => {
+                    let dbg = error.get_dbg();
+                    afb_log_raw!(
+                        Critical,
+                        apiv4,
+                        "binding start fail:{} file: {}:{}:{}",
+                        error.get_info(),
+                        dbg.file,
+                        dbg.line,
+                        dbg.column
+                    );
+                    AFB_FAIL
                 },
             },
-            None => AFB_OK,
+            Ok(None) => AFB_OK,
         },
 
-        cglue::afb_ctlid_afb_ctlid_Class_Ready => match api_ref.ctrlbox {
-            Some(ctrlbox) => match unsafe { (*ctrlbox).ready(api_ref) } {
+        cglue::afb_ctlid_afb_ctlid_Class_Ready => match api_ref.lock_controls() {
+            Err(error) => {
+                afb_log_msg!(Critical, apiv4, &error);
+                AFB_FAIL
+            },
+            Ok(Some(mut ctrlbox)) => match ctrlbox.ready(api_ref) {
                 Ok(()) => AFB_OK,
                 Err(error) => {
                     let dbg = error.get_dbg();
@@ -663,34 +1417,290 @@ pub unsafe extern "C" fn api_controls_cb(
                     AFB_FAIL
                 },
             },
-            None => AFB_OK,
+            Ok(None) => AFB_OK,
         },
 
-        cglue::afb_ctlid_afb_ctlid_Orphan_Event => match api_ref.ctrlbox {
-            Some(ctrlbox) => {
+        cglue::afb_ctlid_afb_ctlid_Orphan_Event => match api_ref.lock_controls() {
+            Err(error) => {
+                afb_log_msg!(Error, apiv4, &error);
+                AFB_OK
+            },
+            Ok(Some(mut ctrlbox)) => {
                 let cbuffer = unsafe { (*ctlarg).orphan_event.name };
                 let cname = unsafe { CStr::from_ptr(cbuffer) };
-                unsafe {
-                    (*ctrlbox).orphan(api_ref, cname.to_str().unwrap());
-                };
+                ctrlbox.orphan(api_ref, cname.to_str().unwrap());
                 AFB_OK
             },
-            None => AFB_OK,
+            Ok(None) => AFB_OK,
         },
 
-        cglue::afb_ctlid_afb_ctlid_Exiting => match api_ref.ctrlbox {
-            Some(ctrlbox) => unsafe { (*ctrlbox).exit(api_ref, (*ctlarg).exiting.code) },
-            None => AFB_OK,
+        cglue::afb_ctlid_afb_ctlid_Exiting => {
+            let code = unsafe { (*ctlarg).exiting.code };
+            if api_ref.drain_timeout > 0 {
+                api_ref.exit_drain();
+            }
+            api_ref.exit_finalize(code)
         },
 
+        // ctlid added by a newer libafb, nothing to do
         _ => {
-            panic!("Rust ApiControl unknown ctlid (hoop!!!)");
+            afb_log_msg!(Warning, apiv4, "api:{} ignoring unknown ctlid:{}", api_ref._uid, ctlid);
+            AFB_OK
         },
     };
 
     status
 }
 
+// unversioned verb name forwarding to one of its versions, updated by runtime registration
+struct AfbVerbVersions {
+    name: &'static str,
+    versions: RwLock<Vec<(&'static str, &'static AfbVerb)>>,
+}
+
+impl AfbVerbVersions {
+    // requested version, highest one when none is given
+    fn select(&self, version: Option<&str>) -> Option<&'static AfbVerb> {
+        let versions = self.versions.read().unwrap_or_else(PoisonError::into_inner);
+        let target = match version {
+            Some(version) => versions.iter().find(|(uid, _)| *uid == version),
+            None => versions.iter().max_by(|a, b| version_cmp(a.0, b.0)),
+        };
+        target.map(|(_, verb)| *verb)
+    }
+
+    // version uids sorted from lowest to highest
+    fn get_versions(&self) -> Vec<&'static str> {
+        let versions = self.versions.read().unwrap_or_else(PoisonError::into_inner);
+        let mut uids: Vec<&'static str> = versions.iter().map(|(uid, _)| *uid).collect();
+        uids.sort_by(|a, b| version_cmp(a, b));
+        uids
+    }
+}
+
+// compare version uids chunk by chunk, digits numerically ("v2" < "v10", "1.2" < "1.10")
+pub(crate) fn version_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    // split into digit/non-digit runs
+    fn chunks(value: &str) -> Vec<(bool, &str)> {
+        let mut chunks: Vec<(bool, &str)> = Vec::new();
+        let mut start = 0;
+        for (idx, chr) in value.char_indices() {
+            let digit = chr.is_ascii_digit();
+            match chunks.last() {
+                Some((last, _)) if *last == digit => {},
+                _ if idx == 0 => chunks.push((digit, "")),
+                _ => {
+                    if let Some(last) = chunks.last_mut() {
+                        last.1 = &value[start..idx];
+                    }
+                    chunks.push((digit, ""));
+                    start = idx;
+                },
+            }
+        }
+        if let Some(last) = chunks.last_mut() {
+            last.1 = &value[start..];
+        }
+        chunks
+    }
+
+    let (achunks, bchunks) = (chunks(a), chunks(b));
+    for ((adigit, achunk), (bdigit, bchunk)) in achunks.iter().zip(bchunks.iter()) {
+        let order = if *adigit && *bdigit {
+            let (atrim, btrim) = (achunk.trim_start_matches('0'), bchunk.trim_start_matches('0'));
+            atrim.len().cmp(&btrim.len()).then_with(|| atrim.cmp(btrim))
+        } else {
+            achunk.cmp(bchunk)
+        };
+        if order != std::cmp::Ordering::Equal {
+            return order;
+        }
+    }
+    achunks.len().cmp(&bchunks.len())
+}
+
+// one dispatcher per versioned verb name, unless a plain verb already uses that name. Called at
+// api init with every verb and by register_verb/register_group with the new ones. On failure
+// dispatchers registered and versions added by this call are removed.
+fn register_versions(api_ref: &AfbApi, apiv4: cglue::afb_api_t, verbs: &[&'static AfbVerb]) -> i32 {
+    let all_verbs = api_ref.get_all_verbs();
+    let mut dispatchers = api_ref.dispatchers.write().unwrap_or_else(PoisonError::into_inner);
+    let registered = dispatchers.len();
+    let mut extended: Vec<(&'static AfbVerbVersions, Vec<(&'static str, &'static AfbVerb)>)> =
+        Vec::new();
+    let mut status = 0;
+    for verb_ref in verbs {
+        let version = match verb_ref.version {
+            Some(version) => version,
+            None => continue,
+        };
+        // registered name is "[group-prefix]version/name"
+        let suffix = format!("{}/{}", version, verb_ref.base_name);
+        let name = match verb_ref.name.strip_suffix(&suffix) {
+            Some(prefix) => format!("{}{}", prefix, verb_ref.base_name),
+            None => continue,
+        };
+        if all_verbs
+            .iter()
+            .any(|verb| verb.name == name || verb.aliases.contains(&name.as_str()))
+        {
+            continue;
+        }
+        if let Some(dispatcher) = dispatchers.iter().find(|dispatcher| dispatcher.name == name) {
+            let mut versions = dispatcher.versions.write().unwrap_or_else(PoisonError::into_inner);
+            if dispatchers[..registered].iter().any(|known| std::ptr::eq(*known, *dispatcher))
+                && !extended.iter().any(|(known, _)| std::ptr::eq(*known, *dispatcher))
+            {
+                extended.push((dispatcher, versions.clone()));
+            }
+            versions.retain(|(uid, _)| *uid != version);
+            versions.push((version, verb_ref));
+            continue;
+        }
+
+        let dispatcher: &'static AfbVerbVersions = Box::leak(Box::new(AfbVerbVersions {
+            name: to_static_str(name),
+            versions: RwLock::new(vec![(version, *verb_ref)]),
+        }));
+        let verb_name = CString::new(dispatcher.name).expect("invalid verb name");
+        let verb_info = CString::new("versioned verb, select with 'version' field").unwrap();
+        // permission of the selected versioned verb is checked by api_versions_cb
+        status = unsafe {
+            cglue::afb_api_add_verb(
+                apiv4,
+                verb_name.as_ptr(),
+                verb_info.as_ptr(),
+                Some(api_versions_cb),
+                dispatcher as *const _ as *mut std::ffi::c_void,
+                AFB_NO_AUTH,
+                0,
+                0,
+            )
+        };
+        if status < 0 {
+            afb_log_msg!(Critical, apiv4, "Fail to register versioned verb={:?}", verb_name);
+            break;
+        }
+        dispatchers.push(dispatcher);
+    }
+    if status < 0 {
+        for dispatcher in dispatchers.drain(registered..) {
+            let verb_name = CString::new(dispatcher.name).expect("invalid verb name");
+            let mut vcbdata: *mut std::ffi::c_void = std::ptr::null_mut();
+            unsafe { cglue::afb_api_del_verb(apiv4, verb_name.as_ptr(), &mut vcbdata) };
+        }
+        for (dispatcher, versions) in extended {
+            *dispatcher.versions.write().unwrap_or_else(PoisonError::into_inner) = versions;
+        }
+    }
+    status
+}
+
+// removed verbs are no longer reachable from their unversioned name
+fn unregister_versions(api_ref: &AfbApi, verbs: &[&'static AfbVerb]) {
+    let dispatchers = api_ref.dispatchers.read().unwrap_or_else(PoisonError::into_inner);
+    for dispatcher in dispatchers.iter() {
+        let mut versions = dispatcher.versions.write().unwrap_or_else(PoisonError::into_inner);
+        versions.retain(|(_, target)| !verbs.iter().any(|verb| std::ptr::eq(*verb, *target)));
+    }
+}
+
+// evaluate the afb_auth tree libafb would check before calling the verb, AfbPermission only
+// builds LOA, permission, and/or nodes
+unsafe fn api_auth_check(request: &AfbRequest, auth: *const AfbAuthV4) -> Result<bool, AfbError> {
+    if auth.is_null() {
+        return Ok(true);
+    }
+    let auth = &*auth;
+    match auth.type_ {
+        cglue::afb_auth_type_afb_auth_LOA => Ok(request.get_loa() >= auth.__bindgen_anon_1.loa),
+        cglue::afb_auth_type_afb_auth_Permission => {
+            let permission = CStr::from_ptr(auth.__bindgen_anon_1.text).to_string_lossy();
+            request.has_permission(&permission)
+        },
+        cglue::afb_auth_type_afb_auth_And => {
+            Ok(api_auth_check(request, auth.__bindgen_anon_1.first)?
+                && api_auth_check(request, auth.next)?)
+        },
+        cglue::afb_auth_type_afb_auth_Or => {
+            Ok(api_auth_check(request, auth.__bindgen_anon_1.first)?
+                || api_auth_check(request, auth.next)?)
+        },
+        _ => Ok(false),
+    }
+}
+
+/// # Safety
+/// `rqtv4` is provided by libafb; `args` must point to an array of `argc` items.
+#[no_mangle]
+pub unsafe extern "C" fn api_versions_cb(
+    rqtv4: cglue::afb_req_t,
+    argc: u32,
+    args: *const cglue::afb_data_t,
+) {
+    afb_builtin_catch_panic("verb-version", rqtv4, || api_versions(rqtv4, argc, args));
+}
+
+// selected version goes through the same dispatch as a direct call to the versioned verb, its
+// permission is checked here as the dispatcher is registered without any
+unsafe fn api_versions(rqtv4: cglue::afb_req_t, argc: u32, args: *const cglue::afb_data_t) {
+    let dispatcher = &*(cglue::afb_req_get_vcbdata(rqtv4) as *const AfbVerbVersions);
+    let api_ref = {
+        let apiv4 = cglue::afb_req_get_api(rqtv4);
+        &*(cglue::afb_api_get_userdata(apiv4) as *const AfbApi)
+    };
+    let arguments = AfbRqtData::new(std::slice::from_raw_parts(args, argc as usize), argc, 0);
+    let version = arguments
+        .get::<JsoncObj>(0)
+        .ok()
+        .and_then(|jquery| jquery.optional::<String>("version").ok().flatten());
+    drop(arguments);
+
+    let verb_ref = match dispatcher.select(version.as_deref()) {
+        Some(verb_ref) => verb_ref,
+        None => {
+            let error = AfbError::new(
+                "verb-version",
+                -4,
+                format!("verb:{} unknown version:{:?}", dispatcher.name, version),
+            );
+            if let Ok(params) = AfbParams::convert(error) {
+                cglue::afb_req_reply(
+                    rqtv4,
+                    -4,
+                    params.arguments.len() as u32,
+                    params.arguments.as_slice().as_ptr(),
+                );
+            }
+            return;
+        },
+    };
+
+    // denied requests never reach verb dispatch, as when libafb rejects them
+    let request = AfbRequest::new(rqtv4, api_ref, verb_ref);
+    match api_auth_check(&request, verb_ref.auth.load(Ordering::Acquire)) {
+        Ok(true) => {},
+        Ok(false) => {
+            let error = AfbError::new(
+                "verb-permission",
+                -13,
+                format!("verb:{} permission denied", verb_ref.name),
+            );
+            request.reply(error, -13);
+            return;
+        },
+        Err(error) => {
+            request.reply(error, -13);
+            return;
+        },
+    }
+    drop(request);
+    api_verb_dispatch(rqtv4, api_ref, verb_ref, argc, args);
+}
+
+// apis still running, last one to exit releases objects created outside of any api
+static AFB_APIS_RUNNING: AtomicUsize = AtomicUsize::new(0);
+
 pub struct AfbApi {
     _uid: &'static str,
     _count: usize,
@@ -700,18 +1710,35 @@ pub struct AfbApi {
     version: &'static str,
     permission: &'static AfbPermission,
     class: &'static str,
-    verbosity: u32,
+    verbosity: AtomicU32,
     do_info: bool,
+    do_apidoc: bool,
+    do_stats: Option<&'static AfbPermission>,
+    do_verbosity: Option<&'static AfbPermission>,
+    #[cfg(feature = "openmetrics")]
+    do_metrics: bool,
+    #[cfg(feature = "openmetrics")]
+    metrics_registry: Option<&'static AfbMetricsRegistry>,
     do_ping: bool,
     do_seal: bool,
     do_concurrency: bool,
-    verbs: Vec<*const AfbVerb>,
+    // api verbs and groups, updated at runtime by register_verb/register_group when not sealed
+    verbs: RwLock<Vec<*const AfbVerb>>,
     evthandlers: Vec<*const AfbEvtHandler>,
     events: Vec<*const AfbEvent>,
-    groups: Vec<*const AfbGroup>,
+    groups: RwLock<Vec<*const AfbGroup>>,
+    dispatchers: RwLock<Vec<&'static AfbVerbVersions>>,
     ctrlbox: Option<*mut dyn AfbApiControls>,
     require_apis: Vec<&'static str>,
     require_classes: Vec<&'static str>,
+    binding: Cell<*const AfbBinding>,
+    data_lock: RwLock<()>,
+    drain_timeout: u32,
+    // libafb requests not yet released, waited for on exit
+    drained: (Mutex<bool>, Condvar),
+    inflight: AtomicUsize,
+    limits: Vec<(&'static str, &'static AfbLimit)>,
+    interceptors: Vec<&'static dyn AfbInterceptor>,
 }
 
 impl AfbApi {
@@ -721,22 +1748,37 @@ impl AfbApi {
             _count: 0,
             _apiv4: Cell::new(0 as cglue::afb_api_t),
             do_info: true,
+            do_apidoc: false,
+            do_stats: None,
+            do_verbosity: None,
+            #[cfg(feature = "openmetrics")]
+            do_metrics: false,
+            #[cfg(feature = "openmetrics")]
+            metrics_registry: None,
             do_seal: true,
             do_ping: true,
             name: uid,
             info: "",
             class: "",
             version: "",
-            verbosity: 0,
+            verbosity: AtomicU32::new(0),
             permission: AfbPermission::new(0),
             do_concurrency: true,
             ctrlbox: None,
-            verbs: Vec::new(),
+            verbs: RwLock::new(Vec::new()),
             events: Vec::new(),
             evthandlers: Vec::new(),
-            groups: Vec::new(),
+            groups: RwLock::new(Vec::new()),
+            dispatchers: RwLock::new(Vec::new()),
             require_apis: Vec::new(),
             require_classes: Vec::new(),
+            binding: Cell::new(std::ptr::null()),
+            data_lock: RwLock::new(()),
+            drain_timeout: 0,
+            drained: (Mutex::new(false), Condvar::new()),
+            inflight: AtomicUsize::new(0),
+            limits: Vec::new(),
+            interceptors: Vec::new(),
         });
         Box::leak(api_box)
     }
@@ -761,6 +1803,45 @@ impl AfbApi {
         self
     }
 
+    /// Builtin `apidoc` verb returning OpenAPI/AsyncAPI documents, off by default.
+    pub fn add_apidoc_cb(&mut self, value: bool) -> &mut Self {
+        self.do_apidoc = value;
+        self
+    }
+
+    /// Builtin `stats` verb returning (or resetting) per verb request metrics, off by default.
+    /// `permission` is required on top of api one.
+    pub fn add_stats_cb(&mut self, permission: &'static AfbPermission) -> &mut Self {
+        self.do_stats = Some(permission);
+        self
+    }
+
+    /// Builtin `verbosity` verb reading/changing api, group, verb and event handler verbosity at
+    /// runtime, off by default. `permission` is required on top of api one.
+    pub fn add_verbosity_cb(&mut self, permission: &'static AfbPermission) -> &mut Self {
+        self.do_verbosity = Some(permission);
+        self
+    }
+
+    /// Builtin `metrics` verb returning OpenMetrics text, off by default.
+    #[cfg(feature = "openmetrics")]
+    pub fn add_metrics_cb(&mut self, value: bool) -> &mut Self {
+        self.do_metrics = value;
+        self
+    }
+
+    /// Binding metrics exported with api verbs metrics by the `metrics` verb.
+    #[cfg(feature = "openmetrics")]
+    pub fn set_metrics_registry(&mut self, registry: &'static AfbMetricsRegistry) -> &mut Self {
+        self.metrics_registry = Some(registry);
+        self
+    }
+
+    #[cfg(feature = "openmetrics")]
+    pub fn get_metrics_registry(&self) -> Option<&'static AfbMetricsRegistry> {
+        self.metrics_registry
+    }
+
     pub fn add_ping_cb(&mut self, value: bool) -> &mut Self {
         self.do_ping = value;
         self
@@ -786,21 +1867,153 @@ impl AfbApi {
         self
     }
 
+    /// On exit, wait up to `ms` for this api in-flight requests to complete before calling
+    /// `AfbApiControls::exit` and releasing tracked objects (default 0: no wait). The exit
+    /// control callback blocks meanwhile, other binder threads keep serving requests.
+    pub fn set_drain_timeout(&mut self, ms: u32) -> &mut Self {
+        self.drain_timeout = ms;
+        self
+    }
+
+    /// Verb and group limits from binding config, keys are verb or group uids:
+    /// `{"verbs":{"uid":{"max_inflight":4}}, "groups":{"uid":{"rate":10,"burst":20}}}`.
+    /// Limits are attached when the api is finalized.
+    #[track_caller]
+    pub fn set_limits(&mut self, jlimits: &JsoncObj) -> Result<&mut Self, AfbError> {
+        for section in ["verbs", "groups"] {
+            let jsection = match jlimits.optional::<JsoncObj>(section)? {
+                Some(jsection) => jsection,
+                None => continue,
+            };
+            for entry in jsection.expand()? {
+                let uid = to_static_str(format!("{}:{}", section, entry.key));
+                self.limits.push((uid, AfbLimit::from_jsonc(uid, &entry.obj)?));
+            }
+        }
+        Ok(self)
+    }
+
+    // attach config limits to matching verbs/groups, unknown uids are rejected
+    #[track_caller]
+    fn apply_limits(&mut self) -> Result<(), AfbError> {
+        for (uid, limit) in &self.limits {
+            let mut found = false;
+            if let Some(verb_uid) = uid.strip_prefix("verbs:") {
+                let groups = self.get_group_slots();
+                let api_verbs = self.get_verb_slots();
+                let verbs = api_verbs
+                    .iter()
+                    .chain(groups.iter().flat_map(|slot| unsafe { &(**slot).verbs }));
+                for slot in verbs {
+                    let verb_ref = unsafe { &mut *(*slot as *mut AfbVerb) };
+                    if verb_ref._uid == verb_uid {
+                        verb_ref.limit = Some(*limit);
+                        found = true;
+                    }
+                }
+            } else if let Some(group_uid) = uid.strip_prefix("groups:") {
+                for slot in self.get_group_slots() {
+                    let group_ref = unsafe { &mut *(slot as *mut AfbGroup) };
+                    if group_ref._uid == group_uid {
+                        group_ref.limit = Some(*limit);
+                        found = true;
+                    }
+                }
+            }
+            if !found {
+                return afb_error!(
+                    "api-limits",
+                    "api:{} no verb/group matching limit {}",
+                    self._uid,
+                    uid
+                );
+            }
+        }
+        Ok(())
+    }
+
+    // user exit callback then release of objects owned by this api
+    fn exit_finalize(&self, code: i32) -> i32 {
+        // exit callback is skipped rather than run concurrently with a guard holder
+        let status = match self.try_lock_controls() {
+            Ok(Some(mut ctrlbox)) => ctrlbox.exit(self, code),
+            Ok(None) => AFB_OK,
+            Err(error) => {
+                afb_log_msg!(Error, self, "api exit callback skipped: {}", error);
+                code
+            },
+        };
+        let last = AFB_APIS_RUNNING.fetch_sub(1, Ordering::AcqRel) == 1;
+        afb_exit_release(self.get_apiv4(), last);
+        status
+    }
+
+    // wait up to drain timeout for in-flight requests, released requests wake us up
+    fn exit_drain(&self) {
+        let deadline = Instant::now() + Duration::from_millis(self.drain_timeout as u64);
+        let (lock, condvar) = &self.drained;
+        let mut draining = lock.lock().unwrap_or_else(PoisonError::into_inner);
+        *draining = true;
+        while self.inflight.load(Ordering::Acquire) > 0 {
+            let now = Instant::now();
+            if now >= deadline {
+                afb_log_msg!(
+                    Warning,
+                    self,
+                    "api exit with {} request(s) in-flight",
+                    self.inflight.load(Ordering::Acquire)
+                );
+                break;
+            }
+            draining = condvar
+                .wait_timeout(draining, deadline - now)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+        *draining = false;
+    }
+
+    // last in-flight request released, wake up a draining exit
+    fn request_released(&self) {
+        if self.inflight.fetch_sub(1, Ordering::AcqRel) == 1 {
+            let (lock, condvar) = &self.drained;
+            if *lock.lock().unwrap_or_else(PoisonError::into_inner) {
+                condvar.notify_all();
+            }
+        }
+    }
+
     pub fn set_verbosity(&mut self, value: i32) -> Result<&mut Self, AfbError> {
-        self.verbosity = verbosity_to_mask(value)?;
+        self.verbosity.store(verbosity_to_mask(value)?, Ordering::Relaxed);
         Ok(self)
     }
 
     pub fn get_verbosity(&self) -> u32 {
-        if self.verbosity == 0 {
-            unsafe { cglue::afb_api_logmask(self.get_apiv4()) as u32 }
-        } else {
-            self.verbosity
+        match self.verbosity.load(Ordering::Relaxed) {
+            0 => unsafe { cglue::afb_api_logmask(self.get_apiv4()) as u32 },
+            mask => mask,
+        }
+    }
+
+    /// Change api verbosity at runtime, verbs, groups and event handlers without their own
+    /// verbosity follow. Level 0 falls back to libafb api logmask.
+    pub fn update_verbosity(&self, level: i32) -> Result<u32, AfbError> {
+        let mask = if level == 0 { 0 } else { verbosity_to_mask(level)? };
+        self.verbosity.store(mask, Ordering::Relaxed);
+        for slot in self.get_verb_slots() {
+            unsafe { &*slot }.follow_verbosity(mask);
+        }
+        for slot in &self.evthandlers {
+            unsafe { &*(*slot) }.follow_verbosity(mask);
+        }
+        for slot in self.get_api_group_slots() {
+            unsafe { &*slot }.follow_verbosity(mask);
         }
+        Ok(mask)
     }
 
     pub fn add_verb(&mut self, verb: &AfbVerb) -> &mut Self {
-        self.verbs.push(verb);
+        self.verbs.get_mut().unwrap_or_else(PoisonError::into_inner).push(verb);
         self
     }
 
@@ -810,7 +2023,7 @@ impl AfbApi {
     }
 
     pub fn add_group(&mut self, group: &AfbGroup) -> &mut Self {
-        self.groups.push(group);
+        self.groups.get_mut().unwrap_or_else(PoisonError::into_inner).push(group);
         self
     }
 
@@ -824,6 +2037,12 @@ impl AfbApi {
         self
     }
 
+    /// Interceptor run around every verb of the api, groups included.
+    pub fn add_interceptor(&mut self, interceptor: Box<dyn AfbInterceptor>) -> &mut Self {
+        self.interceptors.push(Box::leak(interceptor));
+        self
+    }
+
     pub fn require_api(&mut self, value: &'static str) -> &mut Self {
         if !value.is_empty() {
             self.require_apis.push(value);
@@ -842,8 +2061,129 @@ impl AfbApi {
         self._apiv4.set(apiv4);
     }
 
+    // runtime verb add/remove requires a started and unsealed api
+    #[track_caller]
+    fn get_dynamic_apiv4(&self) -> Result<cglue::afb_api_t, AfbError> {
+        let apiv4 = self.get_apiv4();
+        if apiv4.is_null() {
+            return afb_error!(
+                self._uid,
+                "api not started, use add_verb/add_group before finalize"
+            );
+        }
+        if self.do_seal {
+            return afb_error!(
+                self._uid,
+                "api is sealed, use .seal(false) to update verbs at runtime"
+            );
+        }
+        Ok(apiv4)
+    }
+
+    /// Add a verb to a running api (after Pre_Init), api should not be sealed.
+    #[track_caller]
+    pub fn register_verb(&self, verb: &'static AfbVerb) -> Result<&Self, AfbError> {
+        let apiv4 = self.get_dynamic_apiv4()?;
+
+        // write lock serializes runtime updates against info/openapi/verbosity readers
+        let mut verbs = self.verbs.write().unwrap_or_else(PoisonError::into_inner);
+        verb.inherit_verbosity(self.verbosity.load(Ordering::Relaxed));
+        let api_auth = AfbPermisionV4::new(self.permission, AFB_AUTH_DFLT_V4);
+        let status = unsafe { verb.register(apiv4, api_auth) };
+        if status < 0 {
+            return afb_error!(
+                self._uid,
+                "fail to register verb:{} status:{} info:{}",
+                verb.get_uid(),
+                status,
+                afb_error_info(status)
+            );
+        }
+        verbs.push(verb);
+        drop(verbs);
+
+        let status = register_versions(self, apiv4, &[verb]);
+        if status < 0 {
+            // verb is not left half registered
+            unsafe { verb.unregister(apiv4) };
+            let mut verbs = self.verbs.write().unwrap_or_else(PoisonError::into_inner);
+            verbs.retain(|slot| !std::ptr::eq(*slot, verb));
+            return afb_error!(
+                self._uid,
+                "fail to register versioned verb:{} status:{} info:{}",
+                verb.get_uid(),
+                status,
+                afb_error_info(status)
+            );
+        }
+        Ok(self)
+    }
+
+    /// Add a group (verbs, events, handlers) to a running api, api should not be sealed.
+    #[track_caller]
+    pub fn register_group(&self, group: &'static AfbGroup) -> Result<&Self, AfbError> {
+        let apiv4 = self.get_dynamic_apiv4()?;
+
+        let mut groups = self.groups.write().unwrap_or_else(PoisonError::into_inner);
+        let api_auth = AfbPermisionV4::new(self.permission, AFB_AUTH_DFLT_V4);
+        let status = unsafe { group.register(apiv4, api_auth) };
+        if status < 0 {
+            return afb_error!(
+                self._uid,
+                "fail to register group:{} status:{} info:{}",
+                group.get_uid(),
+                status,
+                afb_error_info(status)
+            );
+        }
+        groups.push(group);
+        drop(groups);
+
+        let status = register_versions(self, apiv4, &group.get_all_verbs());
+        if status < 0 {
+            if let Err(error) = group.unregister(self) {
+                afb_log_msg!(Error, self, &error);
+            }
+            return afb_error!(
+                self._uid,
+                "fail to register group:{} versioned verbs status:{} info:{}",
+                group.get_uid(),
+                status,
+                afb_error_info(status)
+            );
+        }
+        Ok(self)
+    }
+
+    /// Remove an api level verb from a running api, api should not be sealed.
+    /// Group verbs are removed with `group.unregister(api)`.
+    #[track_caller]
+    pub fn remove_verb(&self, uid: &str) -> Result<&Self, AfbError> {
+        let apiv4 = self.get_dynamic_apiv4()?;
+
+        let mut verbs = self.verbs.write().unwrap_or_else(PoisonError::into_inner);
+        let verb_ref = match verbs.iter().find(|slot| unsafe { (***slot)._uid } == uid) {
+            Some(slot) => unsafe { &**slot },
+            None => return afb_error!(self._uid, "remove_verb unknown verb uid:{}", uid),
+        };
+        let status = unsafe { verb_ref.unregister(apiv4) };
+        if status < 0 {
+            return afb_error!(
+                self._uid,
+                "fail to remove verb:{} status:{} info:{}",
+                uid,
+                status,
+                afb_error_info(status)
+            );
+        }
+        verbs.retain(|slot| !std::ptr::eq(*slot, verb_ref));
+        unregister_versions(self, &[verb_ref]);
+        Ok(self)
+    }
+
     #[track_caller]
     pub fn finalize(&mut self) -> Result<&AfbApi, AfbError> {
+        self.apply_limits()?;
         let api_name = CString::new(self.name).expect("invalid api name");
         let api_info = CString::new(self.info).expect("invalid api info");
 
@@ -888,6 +2228,150 @@ impl AfbApi {
     pub fn get_version(&self) -> &'static str {
         self.version
     }
+
+    /// OpenAPI 3.1 document: verbs are POST operations on /api/{api}/{verb}, samples are
+    /// request examples and api/group/verb permissions become security requirements. Aliases
+    /// reference their verb path, versioned verbs list their versions on the unversioned name.
+    pub fn get_openapi(&self) -> Result<JsoncObj, AfbError> {
+        let jinfo = JsoncObj::new();
+        jinfo.add("title", self.get_name())?;
+        jinfo.add("version", if self.version.is_empty() { "0" } else { self.version })?;
+        if !self.info.is_empty() {
+            jinfo.add("description", self.info)?;
+        }
+
+        let api_security = permission_to_security(self.permission);
+        let jpaths = JsoncObj::new();
+        openapi_add_verbs(self, &jpaths, "", &api_security, &self.get_verb_slots())?;
+
+        let jtags = JsoncObj::array();
+        for slot in self.get_api_group_slots() {
+            openapi_add_group(self, &jpaths, &jtags, &api_security, unsafe { &*slot })?;
+        }
+        openapi_add_versions(self, &jpaths)?;
+
+        let jloa = JsoncObj::new();
+        jloa.add("type", "http")?;
+        jloa.add("scheme", "bearer")?;
+        jloa.add("description", "client session level of assurance")?;
+        let jpermission = JsoncObj::new();
+        jpermission.add("type", "http")?;
+        jpermission.add("scheme", "bearer")?;
+        jpermission.add("description", "permission checked by afb-binder")?;
+        let jschemes = JsoncObj::new();
+        jschemes.add("afb-loa", jloa)?;
+        jschemes.add("afb-permission", jpermission)?;
+        let jcomponents = JsoncObj::new();
+        jcomponents.add("securitySchemes", jschemes)?;
+
+        let jdoc = JsoncObj::new();
+        jdoc.add("openapi", "3.1.0")?;
+        jdoc.add("info", jinfo)?;
+        if jtags.count()? > 0 {
+            jdoc.add("tags", jtags)?;
+        }
+        jdoc.add("paths", jpaths)?;
+        jdoc.add("components", jcomponents)?;
+        Ok(jdoc)
+    }
+
+    // snapshot of api level verbs, lock is not held while callers walk them
+    fn get_verb_slots(&self) -> Vec<*const AfbVerb> {
+        self.verbs.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    // snapshot of api level groups (without sub-groups)
+    fn get_api_group_slots(&self) -> Vec<*const AfbGroup> {
+        self.groups.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    // api groups and their sub-groups (depth first)
+    fn get_group_slots(&self) -> Vec<*const AfbGroup> {
+        let mut groups = Vec::new();
+        for slot in self.get_api_group_slots() {
+            AfbGroup::collect_groups(slot, &mut groups);
+        }
+        groups
+    }
+
+    pub(crate) fn get_all_groups(&self) -> Vec<&'static AfbGroup> {
+        self.get_group_slots().iter().map(|slot| unsafe { &*(*slot) }).collect()
+    }
+
+    // api verbs followed by group verbs
+    pub(crate) fn get_all_verbs(&self) -> Vec<&'static AfbVerb> {
+        let mut verbs: Vec<&'static AfbVerb> =
+            self.get_verb_slots().iter().map(|slot| unsafe { &*(*slot) }).collect();
+        for group_ref in self.get_all_groups() {
+            verbs.extend(group_ref.verbs.iter().map(|verb| unsafe { &*(*verb) }));
+        }
+        verbs
+    }
+
+    /// Request metrics: api wide totals plus one entry per verb (api and group verbs).
+    pub fn get_stats(&self) -> Result<JsoncObj, AfbError> {
+        let mut totals = AfbVerbStats::default();
+        let jverbs = JsoncObj::new();
+        for verb_ref in self.get_all_verbs() {
+            let stats = verb_ref.metrics.get_stats();
+            totals.calls += stats.calls;
+            totals.success += stats.success;
+            totals.inflight += stats.inflight;
+            totals.unreplied += stats.unreplied;
+            totals.errors.extend(stats.errors);
+            jverbs.add(verb_ref.get_name(), verb_ref.metrics.to_jsonc()?)?;
+        }
+
+        let jerrors = JsoncObj::new();
+        let mut errors = BTreeMap::new();
+        for (code, count) in totals.errors {
+            *errors.entry(code).or_insert(0u64) += count;
+        }
+        for (code, count) in errors {
+            jerrors.add(&code.to_string(), count)?;
+        }
+        let jstats = JsoncObj::new();
+        jstats.add("api", self.get_name())?;
+        jstats.add("calls", totals.calls)?;
+        jstats.add("success", totals.success)?;
+        jstats.add("inflight", totals.inflight)?;
+        jstats.add("unreplied", totals.unreplied)?;
+        jstats.add("errors", jerrors)?;
+        jstats.add("verbs", jverbs)?;
+        Ok(jstats)
+    }
+
+    pub fn reset_stats(&self) {
+        for verb_ref in self.get_all_verbs() {
+            verb_ref.metrics.reset();
+        }
+    }
+
+    /// AsyncAPI 3.0 document exposing api and group events as receive operations.
+    pub fn get_asyncapi(&self) -> Result<JsoncObj, AfbError> {
+        let jinfo = JsoncObj::new();
+        jinfo.add("title", self.get_name())?;
+        jinfo.add("version", if self.version.is_empty() { "0" } else { self.version })?;
+        if !self.info.is_empty() {
+            jinfo.add("description", self.info)?;
+        }
+
+        let jchannels = JsoncObj::new();
+        let joperations = JsoncObj::new();
+        asyncapi_add_events(self, &jchannels, &joperations, &self.events)?;
+        for group_ref in self.get_all_groups() {
+            asyncapi_add_events(self, &jchannels, &joperations, &group_ref.events)?;
+        }
+
+        let jdoc = JsoncObj::new();
+        jdoc.add("asyncapi", "3.0.0")?;
+        jdoc.add("info", jinfo)?;
+        jdoc.add("channels", jchannels)?;
+        jdoc.add("operations", joperations)?;
+        Ok(jdoc)
+    }
+
+    /// Raw access to api userdata bypassing its lock, prefer `api_data`/`api_data_mut`.
     #[allow(clippy::mut_from_ref)]
     pub fn getctrlbox(&self) -> &mut dyn AfbApiControls {
         match self.ctrlbox {
@@ -906,6 +2390,213 @@ impl fmt::Display for AfbApi {
     }
 }
 
+impl AfbApi {
+    #[track_caller]
+    fn get_ctrlbox_ptr<T: 'static>(&self) -> Result<*mut T, AfbError> {
+        let ctrlbox = match self.ctrlbox {
+            Some(ctrlbox) => ctrlbox,
+            None => {
+                return afb_error!(self._uid, "no userdata attached to api, use .set_callback()")
+            },
+        };
+        match unsafe { (*ctrlbox).as_any_mut() }.downcast_mut::<T>() {
+            Some(data) => Ok(data as *mut T),
+            None => afb_error!(self._uid, "api userdata is not a {}", std::any::type_name::<T>()),
+        }
+    }
+
+    // a thread waiting on a lock it already holds would deadlock, it gets an error instead
+    #[track_caller]
+    fn data_read(&self) -> Result<(Option<RwLockReadGuard<'_, ()>>, AfbApiDataHeld), AfbError> {
+        let api = self as *const AfbApi as usize;
+        match AfbApiDataHeld::lookup(api) {
+            Some(true) => {
+                afb_error!(self._uid, "api userdata already borrowed mutably by this thread")
+            },
+            // outer shared guard of this thread protects the data already
+            Some(false) => Ok((None, AfbApiDataHeld::push(api, false))),
+            None => {
+                let lock = self.data_lock.read().unwrap_or_else(PoisonError::into_inner);
+                Ok((Some(lock), AfbApiDataHeld::push(api, false)))
+            },
+        }
+    }
+
+    #[track_caller]
+    fn data_write(&self) -> Result<(RwLockWriteGuard<'_, ()>, AfbApiDataHeld), AfbError> {
+        let api = self as *const AfbApi as usize;
+        if AfbApiDataHeld::lookup(api).is_some() {
+            return afb_error!(self._uid, "api userdata already borrowed by this thread");
+        }
+        let lock = self.data_lock.write().unwrap_or_else(PoisonError::into_inner);
+        Ok((lock, AfbApiDataHeld::push(api, true)))
+    }
+
+    // exit must not wait on a guard kept by a request still in-flight after drain timeout
+    #[track_caller]
+    fn data_try_write(&self) -> Result<(RwLockWriteGuard<'_, ()>, AfbApiDataHeld), AfbError> {
+        let api = self as *const AfbApi as usize;
+        if AfbApiDataHeld::lookup(api).is_some() {
+            return afb_error!(self._uid, "api userdata already borrowed by this thread");
+        }
+        let lock = match self.data_lock.try_write() {
+            Ok(lock) => lock,
+            Err(TryLockError::Poisoned(error)) => error.into_inner(),
+            Err(TryLockError::WouldBlock) => {
+                return afb_error!(self._uid, "api userdata still borrowed by another thread")
+            },
+        };
+        Ok((lock, AfbApiDataHeld::push(api, true)))
+    }
+
+    /// Typed shared access to api userdata (the object given to `set_callback`). Many readers
+    /// may hold it at the same time, it waits while an `api_data_mut` guard is alive. Nested
+    /// shared access from the same thread is allowed, while this thread holds an
+    /// `api_data_mut` guard (or runs an `AfbApiControls` callback) it returns an error.
+    #[track_caller]
+    pub fn api_data<T: 'static>(&self) -> Result<AfbApiDataRef<'_, T>, AfbError> {
+        let data = self.get_ctrlbox_ptr::<T>()?;
+        let (lock, held) = self.data_read()?;
+        Ok(AfbApiDataRef { data: unsafe { &*data }, _lock: lock, _held: held })
+    }
+
+    /// Typed exclusive access to api userdata, waits for every other guard to be released.
+    /// Returns an error when this thread already holds a guard on the same api.
+    #[track_caller]
+    pub fn api_data_mut<T: 'static>(&self) -> Result<AfbApiDataMut<'_, T>, AfbError> {
+        let data = self.get_ctrlbox_ptr::<T>()?;
+        let (lock, held) = self.data_write()?;
+        Ok(AfbApiDataMut { data: unsafe { &mut *data }, _lock: lock, _held: held })
+    }
+
+    // AfbApiControls callbacks have exclusive access to api userdata, None without userdata
+    #[track_caller]
+    fn lock_controls(&self) -> Result<Option<AfbApiDataMut<'_, dyn AfbApiControls>>, AfbError> {
+        let ctrlbox = match self.ctrlbox {
+            Some(ctrlbox) => ctrlbox,
+            None => return Ok(None),
+        };
+        let (lock, held) = self.data_write()?;
+        Ok(Some(AfbApiDataMut { data: unsafe { &mut *ctrlbox }, _lock: lock, _held: held }))
+    }
+
+    // same as lock_controls without waiting for the lock
+    #[track_caller]
+    fn try_lock_controls(&self) -> Result<Option<AfbApiDataMut<'_, dyn AfbApiControls>>, AfbError> {
+        let ctrlbox = match self.ctrlbox {
+            Some(ctrlbox) => ctrlbox,
+            None => return Ok(None),
+        };
+        let (lock, held) = self.data_try_write()?;
+        Ok(Some(AfbApiDataMut { data: unsafe { &mut *ctrlbox }, _lock: lock, _held: held }))
+    }
+
+    /// Binding owning this api when created through `AfbBinding`.
+    #[track_caller]
+    pub fn get_binding(&self) -> Result<&'static AfbBinding, AfbError> {
+        let binding = self.binding.get();
+        if binding.is_null() {
+            afb_error!(self._uid, "api not created from an AfbBinding")
+        } else {
+            Ok(unsafe { &*binding })
+        }
+    }
+}
+
+/// Set of apis exposed by one binding sharing a binding level context. Apis are created at
+/// finalize time, siblings referenced with `require_api` are created first.
+pub struct AfbBinding {
+    _uid: &'static str,
+    info: &'static str,
+    apis: Vec<*mut AfbApi>,
+    context: AfbCtxData,
+}
+
+impl AfbBinding {
+    pub fn new(uid: &'static str) -> &'static mut Self {
+        let binding_box = Box::new(AfbBinding {
+            _uid: uid,
+            info: "",
+            apis: Vec::new(),
+            context: AfbCtxData::new(AFB_NO_DATA),
+        });
+        Box::leak(binding_box)
+    }
+
+    pub fn set_info(&mut self, value: &'static str) -> &mut Self {
+        self.info = value;
+        self
+    }
+
+    /// Binding level context shared by every api, retrieve it with `request.get_binding()`.
+    pub fn set_context<T>(&mut self, ctx: T) -> &mut Self
+    where
+        T: 'static,
+    {
+        self.context = AfbCtxData::new(ctx);
+        self
+    }
+
+    /// Add a not yet finalized api, binding takes it over and creates it in `AfbBinding::finalize`.
+    pub fn add_api(&mut self, api: &'static mut AfbApi) -> &mut Self {
+        self.apis.push(api);
+        self
+    }
+
+    // order apis so that sibling apis referenced by require_api are created first
+    #[track_caller]
+    fn sort_apis(&self) -> Result<Vec<*mut AfbApi>, AfbError> {
+        let mut pending: Vec<*mut AfbApi> = self.apis.clone();
+        let mut sorted: Vec<*mut AfbApi> = Vec::new();
+        while !pending.is_empty() {
+            let ready = pending.iter().position(|slot| {
+                let api_ref = unsafe { &**slot };
+                api_ref.require_apis.iter().all(|name| {
+                    !pending.iter().any(|other| unsafe { (**other).name } == *name)
+                        || api_ref.name == *name
+                })
+            });
+            match ready {
+                Some(idx) => sorted.push(pending.remove(idx)),
+                None => {
+                    let names: Vec<&str> =
+                        pending.iter().map(|slot| unsafe { (**slot).name }).collect();
+                    return afb_error!(
+                        self._uid,
+                        "cyclic require_api between binding apis:{:?}",
+                        names
+                    );
+                },
+            }
+        }
+        Ok(sorted)
+    }
+
+    #[track_caller]
+    pub fn finalize(&mut self) -> Result<&AfbBinding, AfbError> {
+        let sorted = self.sort_apis()?;
+        for slot in sorted {
+            let api_ref = unsafe { &mut *slot };
+            api_ref.binding.set(self);
+            api_ref.finalize()?;
+        }
+        Ok(self)
+    }
+
+    pub fn get_uid(&self) -> &'static str {
+        self._uid
+    }
+    pub fn get_info(&self) -> &'static str {
+        self.info
+    }
+    pub fn get_context(&self) -> &AfbCtxData {
+        &self.context
+    }
+    pub fn get_apis(&self) -> Vec<&'static AfbApi> {
+        self.apis.iter().map(|slot| unsafe { &**slot }).collect()
+    }
+}
+
 /// # Safety
 /// `rqtv4` is a live request handle; `args` points to an array of `argc` items provided by libafb.
 #[no_mangle]
@@ -930,6 +2621,20 @@ pub unsafe extern "C" fn api_verbs_cb(
     api_ref._count += 1; // API-level handle refcount
     verb_ref._count += 1; // Verb-level handle refcount
 
+    api_verb_dispatch(rqtv4, api_ref, verb_ref, argc, args);
+}
+
+// metrics, limits, interceptors, schema then user callback, shared by direct and versioned calls
+unsafe fn api_verb_dispatch(
+    rqtv4: cglue::afb_req_t,
+    api_ref: &'static AfbApi,
+    verb_ref: &'static AfbVerb,
+    argc: u32,
+    args: *const cglue::afb_data_t,
+) {
+    // objects created by the verb callback belong to its api
+    let _scope = AfbExitScope::enter(api_ref.get_apiv4());
+
     // move const **array in something Rust may understand
     let arguments = AfbRqtData::new(
         unsafe { std::slice::from_raw_parts(args as *const cglue::afb_data_t, argc as usize) },
@@ -937,8 +2642,91 @@ pub unsafe extern "C" fn api_verbs_cb(
         0,
     );
 
-    let request = AfbRequest::new(rqtv4, api_ref, verb_ref);
-    let result = (verb_ref.callback)(&request, &arguments, &verb_ref.context);
+    let mut request = AfbRequest::new(rqtv4, api_ref, verb_ref);
+    let state = AfbRqtState::new(rqtv4, api_ref, verb_ref);
+    request.state = Some(state.clone());
+
+    if verb_ref.is_disabled() {
+        let error = AfbError::new(
+            "verb-disabled",
+            -4,
+            format!("verb:{} disabled after {} panics", verb_ref._uid, verb_ref.get_panic_count()),
+        );
+        request.reply(error, -4);
+        return;
+    }
+    verb_ref.warn_deprecated(&request);
+
+    // reject request over verb/group limits before anything else
+    match verb_ref.acquire_limits(&request) {
+        Ok(guards) => *state.limits.lock().unwrap_or_else(PoisonError::into_inner) = guards,
+        Err(error) => {
+            afb_log_msg!(Notice, &request, "verb:{} rejected {}", verb_ref.name, error);
+            request.reply(error, AFB_LIMIT_STATUS);
+            return;
+        },
+    }
+
+    // api then group interceptors, any of them may answer in place of the verb
+    for interceptor in api_ref.interceptors.iter().chain(verb_ref.group_interceptors.iter()) {
+        state
+            .interceptors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(*interceptor);
+        let before =
+            match afb_catch_panic(verb_ref._uid, || interceptor.before(&request, &arguments)) {
+                Ok(before) => before,
+                Err(error) => {
+                    verb_ref.panicked(&request, error);
+                    return;
+                },
+            };
+        match before {
+            Ok(AfbInterceptStatus::Continue) => {},
+            Ok(AfbInterceptStatus::Replied) => return,
+            Err(error) => {
+                let status = if error.get_status() < 0 { error.get_status() } else { -100 };
+                request.reply(error, status);
+                return;
+            },
+        }
+    }
+    drop(state);
+
+    // reject input not matching verb schema before reaching user callback
+    if let Some(jschema) = &verb_ref.input_schema {
+        let validation = match arguments.get::<JsoncObj>(0) {
+            Ok(jinput) => jinput.validate(jschema),
+            // missing or non json argument is checked as a json null
+            Err(_) => JsoncObj::parse("null").and_then(|jnull| jnull.validate(jschema)),
+        };
+        if let Err(error) = validation {
+            afb_log_msg!(Notice, &request, "verb:{} invalid input {}", verb_ref.name, error);
+            request.reply(error, -99);
+            return;
+        }
+    }
+
+    // async verb, future is polled from libafb job loop and replies by itself
+    if let Some(async_cb) = &verb_ref.async_cb {
+        let handle = request.clone();
+        let spawn = || afb_spawn(verb_ref._uid, async_cb(handle, arguments));
+        if let Err(error) = afb_catch_panic(verb_ref._uid, spawn) {
+            verb_ref.panicked(&request, error);
+        }
+        return;
+    }
+
+    let result = match afb_catch_panic(verb_ref._uid, || {
+        (verb_ref.callback)(&request, &arguments, &verb_ref.context)
+    }) {
+        Ok(result) => result,
+        Err(error) => {
+            verb_ref.panicked(&request, error);
+            return;
+        },
+    };
     match result {
         Ok(()) => {},
         Err(error) => {
@@ -963,11 +2751,31 @@ pub struct AfbVerb {
     name: &'static str,
     info: &'static str,
     permission: &'static AfbPermission,
-    verbosity: u32,
+    // libafb auth tree given at registration, checked again by the versioned dispatcher
+    auth: AtomicPtr<AfbAuthV4>,
+    verbosity: AtomicU32,
+    verbosity_set: AtomicBool,
     usage: Option<&'static str>,
     samples: JsoncObj,
     actions: JsoncObj,
-    callback: RqtCallback,
+    input_schema: Option<JsoncObj>,
+    output_schema: Option<JsoncObj>,
+    metrics: AfbVerbMetrics,
+    limit: Option<&'static AfbLimit>,
+    group_limit: Option<&'static AfbLimit>,
+    timeout: u32,
+    noreply_status: i32,
+    group_interceptors: Vec<&'static dyn AfbInterceptor>,
+    panic_limit: u32,
+    panics: AtomicU32,
+    disabled: AtomicBool,
+    base_name: &'static str,
+    version: Option<&'static str>,
+    aliases: Vec<&'static str>,
+    deprecated: Option<(&'static str, &'static str)>,
+    deprecated_warned: Mutex<HashSet<String>>,
+    callback: RqtClosure,
+    async_cb: Option<RqtAsyncCallback>,
     context: AfbCtxData,
 }
 
@@ -978,21 +2786,107 @@ impl AfbVerb {
             _count: 0,
             name: uid,
             info: "",
-            verbosity: 0,
+            verbosity: AtomicU32::new(0),
+            verbosity_set: AtomicBool::new(false),
             permission: AfbPermission::new(0),
+            auth: AtomicPtr::new(AFB_AUTH_DFLT_V4),
             usage: None,
             samples: JsoncObj::array(),
             actions: JsoncObj::array(),
-            callback: rqt_default_cb,
+            input_schema: None,
+            output_schema: None,
+            metrics: AfbVerbMetrics::new(),
+            limit: None,
+            group_limit: None,
+            timeout: 0,
+            noreply_status: -11,
+            group_interceptors: Vec::new(),
+            panic_limit: 0,
+            panics: AtomicU32::new(0),
+            disabled: AtomicBool::new(false),
+            base_name: uid,
+            version: None,
+            aliases: Vec::new(),
+            deprecated: None,
+            deprecated_warned: Mutex::new(HashSet::new()),
+            callback: Box::new(rqt_default_cb),
+            async_cb: None,
             context: AfbCtxData::new(AFB_NO_DATA),
         });
         Box::leak(verb_box)
     }
     pub fn set_name(&mut self, value: &'static str) -> &mut Self {
-        self.name = value;
+        self.base_name = value;
+        self.update_name();
+        self
+    }
+
+    // versioned verbs are registered as "version/name"
+    fn update_name(&mut self) {
+        self.name = match self.version {
+            Some(version) => to_static_str(format!("{}/{}", version, self.base_name)),
+            None => self.base_name,
+        };
+    }
+
+    /// Register verb as `version/name`, api also exposes a `name` verb dispatching requests
+    /// to the version selected by the `version` field of the json argument (default: highest
+    /// version).
+    pub fn set_version(&mut self, version: &'static str) -> &mut Self {
+        self.version = Some(version);
+        self.update_name();
+        self
+    }
+
+    pub fn get_version(&self) -> Option<&'static str> {
+        self.version
+    }
+
+    /// Same callback registered under another name (group prefix applies to aliases too).
+    pub fn add_alias(&mut self, alias: &'static str) -> &mut Self {
+        self.aliases.push(alias);
+        self
+    }
+
+    pub fn get_aliases(&self) -> &[&'static str] {
+        &self.aliases
+    }
+
+    /// Flag verb as deprecated since `since`, `replacement` names the verb to use instead (may
+    /// be empty). A warning is logged once per client session and `info` exposes it.
+    pub fn set_deprecated(&mut self, since: &'static str, replacement: &'static str) -> &mut Self {
+        self.deprecated = Some((since, replacement));
         self
     }
 
+    pub fn get_deprecated(&self) -> Option<(&'static str, &'static str)> {
+        self.deprecated
+    }
+
+    fn warn_deprecated(&self, request: &AfbRequest) {
+        let (since, replacement) = match self.deprecated {
+            Some(deprecated) => deprecated,
+            None => return,
+        };
+        let session = request.get_session_uuid().unwrap_or_else(|_| "anonymous".to_string());
+        let mut warned = self.deprecated_warned.lock().unwrap_or_else(PoisonError::into_inner);
+        // sessions close without notice, keep memory bounded
+        if warned.len() >= 4096 {
+            warned.clear();
+        }
+        if warned.insert(session.clone()) {
+            afb_log_msg!(
+                Warning,
+                request,
+                "verb:{} deprecated since:{} replacement:'{}' (session:{})",
+                self.name,
+                since,
+                replacement,
+                session
+            );
+        }
+    }
+
     pub fn set_info(&mut self, value: &'static str) -> &mut Self {
         self.info = value;
         self
@@ -1033,20 +2927,110 @@ impl AfbVerb {
     }
 
     pub fn set_verbosity(&mut self, value: i32) -> Result<&mut Self, AfbError> {
-        self.verbosity = verbosity_to_mask(value)?;
+        self.verbosity.store(verbosity_to_mask(value)?, Ordering::Relaxed);
+        self.verbosity_set.store(true, Ordering::Relaxed);
         Ok(self)
     }
 
-    pub fn get_verbosity(&self, rqt: &AfbRequest) -> u32 {
-        if self.verbosity == 0 {
-            unsafe { cglue::afb_req_logmask(rqt.get_rqtv4()) as u32 }
+    /// JSON schema enforced on first request argument before calling verb callback.
+    #[track_caller]
+    pub fn set_input_schema<T>(&mut self, schema: T) -> Result<&mut Self, AfbError>
+    where
+        JsoncObj: JsoncImport<T>,
+    {
+        let jvalue = JsoncObj::import(schema)?;
+        if jvalue.is_type(Jtype::Object) {
+            self.input_schema = Some(jvalue);
+            Ok(self)
+        } else {
+            afb_error!("verb-input-schema", "not a valid json schema object")
+        }
+    }
+
+    /// JSON schema describing verb response, published through info verb only.
+    #[track_caller]
+    pub fn set_output_schema<T>(&mut self, schema: T) -> Result<&mut Self, AfbError>
+    where
+        JsoncObj: JsoncImport<T>,
+    {
+        let jvalue = JsoncObj::import(schema)?;
+        if jvalue.is_type(Jtype::Object) {
+            self.output_schema = Some(jvalue);
+            Ok(self)
         } else {
-            self.verbosity
+            afb_error!("verb-output-schema", "not a valid json schema object")
+        }
+    }
+
+    pub fn get_verbosity(&self, rqt: &AfbRequest) -> u32 {
+        match self.verbosity.load(Ordering::Relaxed) {
+            0 => unsafe { cglue::afb_req_logmask(rqt.rqtv4_handle()) as u32 },
+            mask => mask,
+        }
+    }
+
+    /// Change verb verbosity at runtime, level 0 falls back to libafb request logmask and lets
+    /// api/group runtime changes apply again.
+    pub fn update_verbosity(&self, level: i32) -> Result<u32, AfbError> {
+        let mask = if level == 0 { 0 } else { verbosity_to_mask(level)? };
+        self.verbosity.store(mask, Ordering::Relaxed);
+        self.verbosity_set.store(level != 0, Ordering::Relaxed);
+        Ok(mask)
+    }
+
+    // api/group verbosity applies when higher than verb own one
+    fn inherit_verbosity(&self, mask: u32) {
+        self.verbosity.fetch_max(mask, Ordering::Relaxed);
+    }
+
+    // api/group runtime change, ignored when verb verbosity was set explicitly
+    fn follow_verbosity(&self, mask: u32) {
+        if !self.verbosity_set.load(Ordering::Relaxed) {
+            self.verbosity.store(mask, Ordering::Relaxed);
         }
     }
 
-    pub fn set_callback(&mut self, callback: RqtCallback) -> &mut Self {
-        self.callback = callback;
+    /// Verb body: a `RqtCallback` fn using `set_context` data, or a closure capturing typed state.
+    pub fn set_callback<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&AfbRequest, &AfbRqtData, &AfbCtxData) -> Result<(), AfbError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.callback = Box::new(callback);
+        self
+    }
+
+    /// Register an async verb callback, typically an `async fn(AfbRequest, AfbRqtData)`.
+    /// Returned value is used as verb response, error is replied with status -100.
+    pub fn set_async_callback<F, R, T>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(AfbRequest, AfbRqtData) -> R + Send + Sync + 'static,
+        R: Future<Output = Result<T, AfbError>> + Send + 'static,
+        AfbParams: ConvertResponse<T>,
+    {
+        self.async_cb = Some(Box::new(move |request: AfbRequest, args: AfbRqtData| {
+            let future = callback(request.clone(), args);
+            Box::pin(async move {
+                match future.await {
+                    Ok(response) => request.reply(response, 0),
+                    Err(error) => {
+                        let dbg = error.get_dbg();
+                        afb_log_raw!(
+                            Notice,
+                            &request,
+                            "{} file: {}:{}:{}",
+                            error,
+                            dbg.file,
+                            dbg.line,
+                            dbg.column
+                        );
+                        request.reply::<AfbError>(error, -100);
+                    },
+                }
+            })
+        }));
         self
     }
 
@@ -1072,23 +3056,46 @@ impl AfbVerb {
         apiv4: cglue::afb_api_t,
         inherited_auth: *const AfbAuthV4,
     ) -> i32 {
-        let verb_name = CString::new(self.name).expect("invalid verb name");
         let verb_info = CString::new(self.info).expect("invalid verb info");
-
         let verb_permission: *mut AfbAuthV4 = AfbPermisionV4::new(self.permission, inherited_auth);
+        self.auth.store(verb_permission, Ordering::Release);
 
-        unsafe {
-            cglue::afb_api_add_verb(
-                apiv4,
-                verb_name.as_ptr(),
-                verb_info.as_ptr(),
-                Some(api_verbs_cb),
-                self as *const _ as *mut std::ffi::c_void,
-                verb_permission,
-                0,
-                0,
-            )
+        let mut status = 0;
+        for name in std::iter::once(&self.name).chain(self.aliases.iter()) {
+            let verb_name = CString::new(*name).expect("invalid verb name");
+            status = unsafe {
+                cglue::afb_api_add_verb(
+                    apiv4,
+                    verb_name.as_ptr(),
+                    verb_info.as_ptr(),
+                    Some(api_verbs_cb),
+                    self as *const _ as *mut std::ffi::c_void,
+                    verb_permission,
+                    0,
+                    0,
+                )
+            };
+            if status < 0 {
+                break;
+            }
+        }
+        status
+    }
+
+    /// # Safety
+    /// `apiv4` must be the running api where this verb was registered. Verb object is leaked
+    /// and stays valid for requests still in progress.
+    pub unsafe fn unregister(&self, apiv4: cglue::afb_api_t) -> i32 {
+        let mut status = 0;
+        for name in std::iter::once(&self.name).chain(self.aliases.iter()) {
+            let verb_name = CString::new(*name).expect("invalid verb name");
+            let mut vcbdata: *mut std::ffi::c_void = std::ptr::null_mut();
+            let rc = unsafe { cglue::afb_api_del_verb(apiv4, verb_name.as_ptr(), &mut vcbdata) };
+            if rc < 0 {
+                status = rc;
+            }
         }
+        status
     }
 
     pub fn finalize(&mut self) -> Result<&Self, AfbError> {
@@ -1110,6 +3117,87 @@ impl AfbVerb {
     pub fn get_samples(&self) -> JsoncObj {
         self.samples.clone()
     }
+    /// Admission policy checked before verb callback, requests over limit are rejected
+    /// with `AFB_LIMIT_STATUS`.
+    pub fn set_limit(&mut self, limit: &'static AfbLimit) -> &mut Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn get_limit(&self) -> Option<&'static AfbLimit> {
+        self.limit
+    }
+
+    /// Requests not replied within `ms` are replied with -62 (watchdog expire), late replies
+    /// from the verb are dropped. Subcalls issued from the request inherit its deadline.
+    pub fn set_timeout(&mut self, ms: u32) -> &mut Self {
+        self.timeout = ms;
+        self
+    }
+
+    pub fn get_timeout(&self) -> u32 {
+        self.timeout
+    }
+
+    /// Disable the verb after `count` panics (0: never), disabled verbs reply -4 until `enable()`.
+    pub fn set_panic_limit(&mut self, count: u32) -> &mut Self {
+        self.panic_limit = count;
+        self
+    }
+
+    pub fn get_panic_count(&self) -> u32 {
+        self.panics.load(Ordering::Relaxed)
+    }
+
+    pub fn is_disabled(&self) -> bool {
+        self.disabled.load(Ordering::Acquire)
+    }
+
+    /// Re-enable a verb disabled by its panic limit and reset its panic count.
+    pub fn enable(&self) {
+        self.panics.store(0, Ordering::Relaxed);
+        self.disabled.store(false, Ordering::Release);
+    }
+
+    // user code panicked, reply panic as an error and count it against panic limit
+    fn panicked(&self, request: &AfbRequest, error: AfbError) {
+        afb_log_msg!(Critical, request, &error);
+        let count = self.panics.fetch_add(1, Ordering::AcqRel) + 1;
+        if self.panic_limit > 0 && count >= self.panic_limit {
+            self.disabled.store(true, Ordering::Release);
+            afb_log_msg!(Critical, request, "verb:{} disabled after {} panics", self._uid, count);
+        }
+        if !request.is_replied() {
+            request.reply(error, -100);
+        }
+    }
+
+    /// Status replied when the last request handle is dropped without reply (default -11).
+    pub fn set_noreply_status(&mut self, status: i32) -> &mut Self {
+        self.noreply_status = status;
+        self
+    }
+
+    // acquire verb then group limit slots, released with request state
+    #[track_caller]
+    fn acquire_limits(&self, request: &AfbRequest) -> Result<Vec<AfbLimitGuard>, AfbError> {
+        let mut guards = Vec::new();
+        for limit in [self.limit, self.group_limit].into_iter().flatten() {
+            let session = if limit.need_session() { request.get_session_uuid().ok() } else { None };
+            guards.push(limit.acquire(session.as_deref())?);
+        }
+        Ok(guards)
+    }
+
+    pub fn get_metrics(&self) -> &AfbVerbMetrics {
+        &self.metrics
+    }
+    pub fn get_input_schema(&self) -> Option<JsoncObj> {
+        self.input_schema.clone()
+    }
+    pub fn get_output_schema(&self) -> Option<JsoncObj> {
+        self.output_schema.clone()
+    }
     pub fn get_action(&self) -> JsoncObj {
         self.actions.clone()
     }
@@ -1121,18 +3209,232 @@ impl fmt::Display for AfbVerb {
     }
 }
 
+// request state is carried by every AfbRequest handle and by subcalls of the request. Only raw
+// handles given out through `get_rqtv4` are registered here, so `from_raw` finds their state back
+// while the request is alive; verbs that never expose a raw handle do not touch this map.
+static AFB_RQT_ESCAPED: Mutex<BTreeMap<usize, Weak<AfbRqtState>>> = Mutex::new(BTreeMap::new());
+static AFB_RQT_ESCAPED_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) struct AfbRqtState {
+    rqtv4: usize,
+    api: &'static AfbApi,
+    verb: &'static AfbVerb,
+    start: Instant,
+    replied: AtomicBool,
+    escaped: AtomicBool,
+    limits: Mutex<Vec<AfbLimitGuard>>,
+    deadline: Option<Instant>,
+    watchdog: AtomicI32,
+    interceptors: Mutex<Vec<&'static dyn AfbInterceptor>>,
+}
+
+// api and verbs are static and only touched through their thread safe counters
+unsafe impl Send for AfbRqtState {}
+unsafe impl Sync for AfbRqtState {}
+
+impl AfbRqtState {
+    fn new(rqtv4: cglue::afb_req_t, api: &'static AfbApi, verb: &'static AfbVerb) -> Arc<Self> {
+        verb.metrics.started();
+        api.inflight.fetch_add(1, Ordering::AcqRel);
+        let start = Instant::now();
+        let state = Arc::new(AfbRqtState {
+            rqtv4: rqtv4 as usize,
+            api,
+            verb,
+            start,
+            replied: AtomicBool::new(false),
+            escaped: AtomicBool::new(false),
+            limits: Mutex::new(Vec::new()),
+            deadline: (verb.timeout > 0)
+                .then(|| start + Duration::from_millis(verb.timeout as u64)),
+            watchdog: AtomicI32::new(0),
+            interceptors: Mutex::new(Vec::new()),
+        });
+        if verb.timeout > 0 {
+            state.arm_watchdog();
+        }
+        state
+    }
+
+    // delayed job holding a weak reference, it does not keep the request alive
+    fn arm_watchdog(self: &Arc<Self>) {
+        let handle = Weak::into_raw(Arc::downgrade(self));
+        let jobid = unsafe {
+            cglue::afb_job_post(
+                self.verb.timeout as i64,
+                0,
+                Some(api_rqt_watchdog_cb),
+                handle as *mut std::ffi::c_void,
+                std::ptr::null_mut(),
+            )
+        };
+        if jobid <= 0 {
+            let _ = unsafe { Weak::from_raw(handle) };
+            afb_log_msg!(Error, None, "verb:{} fail to arm request watchdog", self.verb._uid);
+            return;
+        }
+        self.watchdog.store(jobid, Ordering::Release);
+    }
+
+    fn disarm_watchdog(&self) {
+        let jobid = self.watchdog.swap(0, Ordering::AcqRel);
+        if jobid > 0 {
+            unsafe { cglue::afb_job_abort(jobid) };
+        }
+    }
+
+    // interceptors `after` in reverse order, lock is released before calling them
+    fn run_interceptors(&self, request: &AfbRequest, status: i32, params: &AfbParams) {
+        let interceptors =
+            std::mem::take(&mut *self.interceptors.lock().unwrap_or_else(PoisonError::into_inner));
+        for interceptor in interceptors.iter().rev() {
+            let after = || interceptor.after(request, status, params);
+            if let Err(error) = afb_catch_panic(self.verb._uid, after) {
+                afb_log_msg!(Critical, request, &error);
+            }
+        }
+    }
+
+    fn remaining_time(&self) -> Option<Duration> {
+        self.deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    // subcalls from a request past its deadline fail without reaching target api
+    #[track_caller]
+    pub(crate) fn subcall_remaining(
+        state: Option<&Arc<Self>>,
+        apiname: &CString,
+        verbname: &CString,
+    ) -> Result<Option<Duration>, AfbError> {
+        let remaining = state.and_then(|state| state.remaining_time());
+        if remaining == Some(Duration::ZERO) {
+            return Err(AfbError::new(
+                "rqt-timeout",
+                -62,
+                format!(
+                    "request deadline expired before subcall api:{:?} verb:{:?}",
+                    apiname, verbname
+                ),
+            ));
+        }
+        Ok(remaining)
+    }
+
+    // raw handle leaves Rust control, register state once so `from_raw` can find it back
+    fn escape(self: &Arc<Self>) {
+        if self.escaped.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        AFB_RQT_ESCAPED
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(self.rqtv4, Arc::downgrade(self));
+        AFB_RQT_ESCAPED_COUNT.fetch_add(1, Ordering::AcqRel);
+    }
+
+    // no lock taken unless some request handed out a raw handle
+    pub(crate) fn lookup(rqtv4: cglue::afb_req_t) -> Option<Arc<Self>> {
+        if AFB_RQT_ESCAPED_COUNT.load(Ordering::Acquire) == 0 {
+            return None;
+        }
+        AFB_RQT_ESCAPED
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&(rqtv4 as usize))
+            .and_then(Weak::upgrade)
+    }
+
+    // return false when request was already replied
+    fn set_replied(&self, status: i32) -> bool {
+        if self.replied.swap(true, Ordering::AcqRel) {
+            return false;
+        }
+        self.disarm_watchdog();
+        self.verb.metrics.replied(status, self.start.elapsed());
+        self.limits.lock().unwrap_or_else(PoisonError::into_inner).clear();
+        true
+    }
+}
+
+impl Drop for AfbRqtState {
+    fn drop(&mut self) {
+        if self.escaped.load(Ordering::Acquire) {
+            let mut escaped = AFB_RQT_ESCAPED.lock().unwrap_or_else(PoisonError::into_inner);
+            // libafb may already reuse this handle address for a newer escaped request
+            if escaped.get(&self.rqtv4).is_some_and(|weak| weak.strong_count() == 0) {
+                escaped.remove(&self.rqtv4);
+            }
+            drop(escaped);
+            AFB_RQT_ESCAPED_COUNT.fetch_sub(1, Ordering::AcqRel);
+        }
+        self.disarm_watchdog();
+        if !self.replied.load(Ordering::Acquire) {
+            self.verb.metrics.dropped();
+        }
+        self.api.request_released();
+    }
+}
+
+/// # Safety
+/// `userdata` must come from `Weak::into_raw` on the request state; the reference is consumed here.
+#[no_mangle]
+pub unsafe extern "C" fn api_rqt_watchdog_cb(signal: i32, userdata: *mut std::os::raw::c_void) {
+    let weak = Weak::from_raw(userdata as *const AfbRqtState);
+    // aborted because request was replied or released
+    if signal != 0 {
+        return;
+    }
+    let state = match weak.upgrade() {
+        Some(state) => state,
+        None => return,
+    };
+    state.watchdog.store(0, Ordering::Release);
+    if state.replied.load(Ordering::Acquire) {
+        return;
+    }
+
+    // timeout reply runs interceptors, nothing unwinds into libafb
+    let result = afb_catch_panic(state.verb._uid, || {
+        let request = AfbRequest::with_state(state.rqtv4 as cglue::afb_req_t, state.clone());
+        let error = AfbError::new(
+            "rqt-timeout",
+            -62,
+            format!("verb:{} no reply within {}ms", state.verb._uid, state.verb.timeout),
+        );
+        afb_log_msg!(Warning, &request, &error);
+        request.reply(error, -62);
+    });
+    if let Err(error) = result {
+        afb_log_msg!(Critical, None, &error);
+    }
+}
+
 pub struct AfbRequest {
     _rqtv4: cglue::afb_req_t,
     api: &'static AfbApi,
     verb: &'static AfbVerb,
+    pub(crate) state: Option<Arc<AfbRqtState>>,
+    // where this handle was created, reported when request is dropped without reply
+    location: &'static Location<'static>,
+    // legacy session objects handed out as `&mut` by this handle
+    pub(crate) session_borrows: RefCell<Vec<crate::sessionv4::AfbRqtSessionBorrow>>,
 }
 
+// libafb request handles are refcounted and thread safe; api and verb are static
+// descriptors frozen before the api is ready. This lets async verbs move their
+// request across binder threads.
+unsafe impl Send for AfbRequest {}
+
 impl Clone for AfbRequest {
+    #[track_caller]
     fn clone(&self) -> Self {
         AfbRequest {
             _rqtv4: unsafe { cglue::afb_req_addref(self._rqtv4) },
             verb: self.verb,
             api: self.api,
+            state: self.state.clone(),
+            location: Location::caller(),
+            session_borrows: RefCell::new(Vec::new()),
         }
     }
 }
@@ -1140,16 +3442,40 @@ impl Clone for AfbRequest {
 #[doc(hidden)]
 impl Drop for AfbRequest {
     fn drop(&mut self) {
+        // last handle of a request never replied answers in place of the verb
+        if let Some(state) = self.state.take().and_then(Arc::into_inner) {
+            if state.set_replied(self.verb.noreply_status) {
+                let error = AfbError::new(
+                    "rqt-no-reply",
+                    self.verb.noreply_status,
+                    format!(
+                        "verb:{} request dropped without reply, last handle from {}:{}",
+                        self.verb._uid,
+                        self.location.file(),
+                        self.location.line()
+                    ),
+                );
+                afb_log_msg!(Warning, &*self, &error);
+                if let Ok(params) = AfbParams::convert(error) {
+                    state.run_interceptors(self, self.verb.noreply_status, &params);
+                    unsafe {
+                        cglue::afb_req_reply(
+                            self._rqtv4,
+                            self.verb.noreply_status,
+                            params.arguments.len() as u32,
+                            params.arguments.as_slice().as_ptr(),
+                        )
+                    };
+                }
+            }
+        }
         unsafe {
             cglue::afb_req_unref(self._rqtv4);
         }
     }
 }
 
-// Rust dynamic object are fat pointer and should be encapculated before passing to C
-struct AfbRqtSessionWrap {
-    inner: Box<dyn AfbRqtSession>,
-}
+/// Legacy per-api session object. Typed sessions (`AfbSession<T>`) require `Send` values.
 pub trait AfbRqtSession {
     fn as_any(&mut self) -> &mut dyn Any;
     fn closing(&mut self) {}
@@ -1158,68 +3484,132 @@ pub trait AfbRqtSession {
 impl AfbRequest {
     /// # Safety
     /// `rqtv4` must be a live libafb request handle. Caller ensures its validity.
+    #[track_caller]
     pub unsafe fn new(
         rqtv4: cglue::afb_req_t,
         api: &'static AfbApi,
         verb: &'static AfbVerb,
     ) -> Self {
-        AfbRequest { _rqtv4: cglue::afb_req_addref(rqtv4), verb, api }
+        AfbRequest {
+            _rqtv4: cglue::afb_req_addref(rqtv4),
+            verb,
+            api,
+            state: None,
+            location: Location::caller(),
+            session_borrows: RefCell::new(Vec::new()),
+        }
     }
 
+    // legacy session object is handed out as `&mut`, it is neither expired, replaced by another
+    // request nor dropped while this handle lives
     #[track_caller]
     #[allow(clippy::mut_from_ref)]
     pub fn set_session(
         &self,
         value: Box<dyn AfbRqtSession>,
     ) -> Result<&mut dyn AfbRqtSession, AfbError> {
-        let wrapper = Box::new(AfbRqtSessionWrap { inner: value });
-        let session = Box::leak(wrapper);
-        let status = unsafe {
-            cglue::afb_req_context_set(
-                self.get_rqtv4(),
-                session as *const _ as *mut ::std::os::raw::c_void,
-                Some(free_session_cb),
-                session as *const _ as *mut ::std::os::raw::c_void,
-            )
+        // a restored object is replaced like a live one
+        #[cfg(feature = "session-persist")]
+        self.claim_api_session();
+
+        // session context is shared with typed session values (see sessionv4)
+        let (previous, inner) = match self.with_session_wrap(true, |wrap| {
+            #[cfg(feature = "session-persist")]
+            self.track_api_session(wrap);
+            let mut state = wrap.lock();
+            // another request still works on current object
+            if self.session_borrowers(wrap, &state) > 0 {
+                return Err(value);
+            }
+            let previous = state.inner.replace(value);
+            self.borrow_session(wrap, &mut state);
+            let inner = state.inner.as_mut().map(|inner| inner.as_mut() as *mut dyn AfbRqtSession);
+            Ok((previous, inner))
+        }) {
+            Some(Ok(result)) => result,
+            Some(Err(_)) => return afb_error!("rqt-session-busy", "session object still in use"),
+            None => return afb_error!("rqt-session-exist", "request fail to create session"),
         };
-        if status < 0 {
-            afb_error!("rqt-session-exist", "request fail to create session")
-        } else {
-            Ok(session.inner.as_mut())
+        crate::sessionv4::mark_dirty();
+        if let Some(mut previous) = previous {
+            previous.closing();
+        }
+        match inner {
+            Some(inner) => Ok(unsafe { &mut *inner }),
+            None => afb_error!("rqt-session-exist", "request fail to create session"),
         }
     }
 
     #[track_caller]
     pub fn drop_session(&self) -> Result<(), AfbError> {
-        let status = unsafe { cglue::afb_req_context_drop(self.get_rqtv4()) };
-        if status < 0 {
-            afb_error!("rqt-session-missing", "request session not defined")
-        } else {
-            Ok(())
+        // emptiness check and context drop happen under session lock
+        let inner = self.with_session_wrap(false, |wrap| {
+            let mut state = wrap.lock();
+            // another request still works on current object
+            if self.session_borrowers(wrap, &state) > 0 {
+                return Err(());
+            }
+            let inner = state.inner.take();
+            // typed session values keep context alive
+            if inner.is_some() && state.is_empty() {
+                drop(state);
+                unsafe { cglue::afb_req_context_drop(self.rqtv4_handle()) };
+            }
+            Ok(inner)
+        });
+        match inner {
+            Some(Err(())) => afb_error!("rqt-session-busy", "session object still in use"),
+            Some(Ok(Some(mut inner))) => {
+                crate::sessionv4::mark_dirty();
+                inner.closing();
+                Ok(())
+            },
+            Some(Ok(None)) | None => {
+                afb_error!("rqt-session-missing", "request session not defined")
+            },
         }
     }
 
     #[allow(clippy::mut_from_ref)]
     #[track_caller]
     pub fn get_session(&self) -> Result<&mut dyn AfbRqtSession, AfbError> {
-        let session = std::ptr::null_mut::<::std::os::raw::c_void>();
-        let status = unsafe {
-            cglue::afb_req_context_get(
-                self.get_rqtv4(),
-                &session as *const _ as *mut *mut ::std::os::raw::c_void,
-            )
-        };
-        if status < 0 {
-            afb_error!("rqt-session-missing", "request session does not exit")
-        } else {
-            let session = unsafe { &mut *(session as *mut AfbRqtSessionWrap) };
-            Ok(session.inner.as_mut())
+        #[cfg(feature = "session-persist")]
+        self.claim_api_session();
+
+        let inner = self.with_session_wrap(false, |wrap| {
+            let mut state = wrap.lock();
+            state.inner.as_ref()?;
+            // object stays in place while this handle lives (see AfbRqtSessionBorrow)
+            self.borrow_session(wrap, &mut state);
+            state.inner.as_mut().map(|inner| inner.as_mut() as *mut dyn AfbRqtSession)
+        });
+        match inner.flatten() {
+            Some(inner) => {
+                // object is handed out as `&mut`, any access may change it
+                crate::sessionv4::mark_dirty();
+                Ok(unsafe { &mut *inner })
+            },
+            None => afb_error!("rqt-session-missing", "request session does not exit"),
         }
     }
 
     /// # Safety
     /// `rqtv4` must be a live libafb request handle. Caller ensures its validity.
+    #[track_caller]
     pub unsafe fn from_raw(rqtv4: AfbRqtV4) -> Self {
+        // handles rebuilt from a raw rqtv4 share state only when it was given out by get_rqtv4,
+        // state also knows the verb of versioned requests
+        if let Some(state) = AfbRqtState::lookup(rqtv4) {
+            return AfbRequest {
+                _rqtv4: rqtv4,
+                verb: state.verb,
+                api: state.api,
+                state: Some(state),
+                location: Location::caller(),
+                session_borrows: RefCell::new(Vec::new()),
+            };
+        }
+
         // extract api_ref from libafb
         let apiv4 = cglue::afb_req_get_api(rqtv4);
         let api_data = cglue::afb_api_get_userdata(apiv4);
@@ -1229,7 +3619,14 @@ impl AfbRequest {
         let verb_ctx = cglue::afb_req_get_vcbdata(rqtv4);
         let verb_ref = &mut *(verb_ctx as *mut AfbVerb);
 
-        AfbRequest { _rqtv4: rqtv4, verb: verb_ref, api: api_ref }
+        AfbRequest {
+            _rqtv4: rqtv4,
+            verb: verb_ref,
+            api: api_ref,
+            state: None,
+            location: Location::caller(),
+            session_borrows: RefCell::new(Vec::new()),
+        }
     }
 
     pub fn get_uid(&self) -> String {
@@ -1248,12 +3645,66 @@ impl AfbRequest {
         self.api._apiv4.get()
     }
 
+    /// Raw libafb handle, a request rebuilt from it with `from_raw` keeps deadline and reply tracking.
     pub fn get_rqtv4(&self) -> cglue::afb_req_t {
+        if let Some(state) = &self.state {
+            state.escape();
+        }
+        self._rqtv4
+    }
+
+    // raw handle for libafb calls that never hand it back to user code
+    pub(crate) fn rqtv4_handle(&self) -> cglue::afb_req_t {
         self._rqtv4
     }
 
+    // handle sharing an already known request state
+    #[track_caller]
+    unsafe fn with_state(rqtv4: cglue::afb_req_t, state: Arc<AfbRqtState>) -> Self {
+        AfbRequest {
+            _rqtv4: cglue::afb_req_addref(rqtv4),
+            verb: state.verb,
+            api: state.api,
+            state: Some(state),
+            location: Location::caller(),
+            session_borrows: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Raw untyped access to api userdata bypassing its lock.
+    #[deprecated(note = "use api_data::<T>(), api_data_mut::<T>() or api_data_any()")]
+    #[allow(clippy::mut_from_ref)]
     pub fn get_apidata(&self) -> &mut dyn Any {
-        self.get_api().getctrlbox().as_any()
+        self.get_api().getctrlbox().as_any_mut()
+    }
+
+    /// Exclusive untyped access to api userdata, same lock as `api_data_mut`.
+    #[track_caller]
+    pub fn api_data_any(&self) -> Result<AfbApiDataMut<'static, dyn Any>, AfbError> {
+        let api = self.get_api();
+        let ctrlbox = match api.lock_controls()? {
+            Some(ctrlbox) => ctrlbox,
+            None => {
+                return afb_error!(api._uid, "no userdata attached to api, use .set_callback()")
+            },
+        };
+        let AfbApiDataMut { data, _lock, _held } = ctrlbox;
+        Ok(AfbApiDataMut { data: data.as_any_mut(), _lock, _held })
+    }
+
+    #[track_caller]
+    pub fn get_binding(&self) -> Result<&'static AfbBinding, AfbError> {
+        self.api.get_binding()
+    }
+
+    #[track_caller]
+    pub fn api_data<T: 'static>(&self) -> Result<AfbApiDataRef<'static, T>, AfbError> {
+        self.api.api_data::<T>()
+    }
+
+    #[track_caller]
+    pub fn api_data_mut<T: 'static>(&self) -> Result<AfbApiDataMut<'static, T>, AfbError> {
+        self.api.api_data_mut::<T>()
     }
 
     #[track_caller]
@@ -1272,11 +3723,42 @@ impl AfbRequest {
         }
     }
 
+    /// Client session level of assurance.
+    pub fn get_loa(&self) -> u32 {
+        unsafe { cglue::afb_req_session_get_LOA(self._rqtv4) }
+    }
+
+    /// Check a permission string for request client (e.g. per-resource ACL). Never blocks:
+    /// permissions libafb cannot answer at once (remote authority) return an
+    /// `rqt-permission-pending` error, use `has_permission_async` for them.
+    #[track_caller]
+    pub fn has_permission(&self, permission: &str) -> Result<bool, AfbError> {
+        AfbPermissionState::check(self, permission)?.answered()
+    }
+
+    pub fn is_replied(&self) -> bool {
+        match &self.state {
+            Some(state) => state.replied.load(Ordering::Acquire),
+            None => false,
+        }
+    }
+
+    /// Time left before verb timeout expires, None when verb has no timeout.
+    pub fn remaining_time(&self) -> Option<Duration> {
+        self.state.as_ref()?.remaining_time()
+    }
+
     pub fn get_client_info(&self) -> Result<JsoncObj, AfbError> {
         let jso = unsafe { cglue::afb_req_get_client_info(self._rqtv4) as *mut std::ffi::c_void };
         JsoncObj::import(jso)
     }
 
+    /// Client session uuid as reported by libafb client info.
+    #[track_caller]
+    pub fn get_session_uuid(&self) -> Result<String, AfbError> {
+        self.get_client_info()?.get::<String>("uuid")
+    }
+
     pub fn add_ref(&self) -> Self {
         self.clone()
     }
@@ -1287,6 +3769,8 @@ impl AfbRequest {
         }
     }
 
+    /// Reply once, further replies (or a reply after verb timeout) are dropped and logged.
+    #[track_caller]
     pub fn reply<T>(&self, args: T, status: i32)
     where
         AfbParams: ConvertResponse<T>,
@@ -1299,6 +3783,25 @@ impl AfbRequest {
             },
             Ok(data) => data,
         };
+        if let Some(state) = &self.state {
+            if !state.set_replied(status) {
+                let location = Location::caller();
+                let error = AfbError::new(
+                    "rqt-reply-twice",
+                    status,
+                    format!(
+                        "verb:{} request already replied (or timed out), reply status:{} from {}:{} dropped",
+                        self.verb._uid,
+                        status,
+                        location.file(),
+                        location.line()
+                    ),
+                );
+                afb_log_msg!(Error, self, &error);
+                return;
+            }
+            state.run_interceptors(self, status, &params);
+        }
         unsafe {
             cglue::afb_req_reply(
                 self._rqtv4,
@@ -1338,7 +3841,10 @@ impl<'a> AfbEventMsg<'a> {
     }
 
     pub fn get_verbosity(&self) -> u32 {
-        self.handler.get_verbosity()
+        match self.handler.get_verbosity() {
+            0 => self.api.get_verbosity(),
+            mask => mask,
+        }
     }
 
     pub fn get_uid(&'a self) -> &'a str {
@@ -1427,7 +3933,11 @@ pub extern "C" fn api_events_cb(
     );
 
     // call event calback
-    let result = (handler_ref.callback)(&event, &mut arguments, &handler_ref.context);
+    let uid = handler_ref._uid;
+    let result = afb_catch_panic(uid, || {
+        (handler_ref.callback)(&event, &mut arguments, &handler_ref.context)
+    })
+    .and_then(|result| result);
     match result {
         Ok(()) => {},
         Err(error) => {
@@ -1449,6 +3959,9 @@ pub extern "C" fn api_events_cb(
 pub type EvtCallback =
     fn(evt: &AfbEventMsg, args: &AfbRqtData, ctx: &AfbCtxData) -> Result<(), AfbError>;
 
+pub type EvtClosure =
+    Box<dyn Fn(&AfbEventMsg, &AfbRqtData, &AfbCtxData) -> Result<(), AfbError> + Send + Sync>;
+
 #[track_caller]
 fn evt_default_cb(
     evt: &AfbEventMsg,
@@ -1461,10 +3974,11 @@ fn evt_default_cb(
 pub struct AfbEvtHandler {
     _uid: &'static str,
     _count: usize,
-    verbosity: u32,
+    verbosity: AtomicU32,
+    verbosity_set: AtomicBool,
     pattern: &'static str,
     info: &'static str,
-    callback: EvtCallback,
+    callback: EvtClosure,
     context: AfbCtxData,
 }
 
@@ -1473,10 +3987,11 @@ impl AfbEvtHandler {
         let event_box = Box::new(AfbEvtHandler {
             _uid: uid,
             _count: 0,
-            verbosity: 0,
+            verbosity: AtomicU32::new(0),
+            verbosity_set: AtomicBool::new(false),
             pattern: uid,
             info: "",
-            callback: evt_default_cb,
+            callback: Box::new(evt_default_cb),
             context: AfbCtxData::new(AFB_NO_DATA),
         });
         Box::leak(event_box)
@@ -1493,16 +4008,43 @@ impl AfbEvtHandler {
     }
 
     pub fn set_verbosity(&mut self, value: i32) -> Result<&mut Self, AfbError> {
-        self.verbosity = verbosity_to_mask(value)?;
+        self.verbosity.store(verbosity_to_mask(value)?, Ordering::Relaxed);
+        self.verbosity_set.store(true, Ordering::Relaxed);
         Ok(self)
     }
 
+    /// Handler own mask, 0 when event messages use api verbosity.
     pub fn get_verbosity(&self) -> u32 {
-        self.verbosity
+        self.verbosity.load(Ordering::Relaxed)
     }
 
-    pub fn set_callback(&mut self, callback: EvtCallback) -> &mut Self {
-        self.callback = callback;
+    /// Change event handler verbosity at runtime, level 0 falls back to api verbosity.
+    pub fn update_verbosity(&self, level: i32) -> Result<u32, AfbError> {
+        let mask = if level == 0 { 0 } else { verbosity_to_mask(level)? };
+        self.verbosity.store(mask, Ordering::Relaxed);
+        self.verbosity_set.store(level != 0, Ordering::Relaxed);
+        Ok(mask)
+    }
+
+    fn inherit_verbosity(&self, mask: u32) {
+        self.verbosity.fetch_max(mask, Ordering::Relaxed);
+    }
+
+    fn follow_verbosity(&self, mask: u32) {
+        if !self.verbosity_set.load(Ordering::Relaxed) {
+            self.verbosity.store(mask, Ordering::Relaxed);
+        }
+    }
+
+    /// Event message handler, given as an `EvtCallback` fn or a closure with its own captures.
+    pub fn set_callback<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&AfbEventMsg, &AfbRqtData, &AfbCtxData) -> Result<(), AfbError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.callback = Box::new(callback);
         self
     }
 
@@ -1540,6 +4082,14 @@ impl AfbEvtHandler {
         }
     }
 
+    /// # Safety
+    /// `apiv4` must be the running api where this handler was registered.
+    pub unsafe fn unregister(&self, apiv4: cglue::afb_api_t) -> i32 {
+        let event_pattern = CString::new(self.pattern).expect("invalid event pattern");
+        let mut closure: *mut std::ffi::c_void = std::ptr::null_mut();
+        unsafe { cglue::afb_api_event_handler_del(apiv4, event_pattern.as_ptr(), &mut closure) }
+    }
+
     // return object getter trait to prevent any malicious modification
     pub fn finalize(&mut self) -> Result<&AfbEvtHandler, AfbError> {
         Ok(self)
@@ -1579,6 +4129,7 @@ impl GetApiV4<&&mut AfbApi> for AfbEvent {
 
 pub struct AfbEvent {
     _uid: &'static str,
+    info: &'static str,
     _evtv4: AfbEvtV4,
     _apiv4: AfbApiV4,
     verbosity: u32,
@@ -1588,6 +4139,7 @@ impl AfbEvent {
     pub fn new(uid: &'static str) -> &'static mut Self {
         let evt_box = Box::new(AfbEvent {
             _uid: uid,
+            info: "",
             _evtv4: 0 as AfbEvtV4,
             _apiv4: 0 as AfbApiV4,
             verbosity: 0,
@@ -1595,6 +4147,15 @@ impl AfbEvent {
         Box::leak(evt_box)
     }
 
+    pub fn set_info(&mut self, value: &'static str) -> &mut Self {
+        self.info = value;
+        self
+    }
+
+    pub fn get_info(&self) -> &'static str {
+        self.info
+    }
+
     pub fn get_verbosity(&self) -> u32 {
         self.verbosity
     }
@@ -1611,6 +4172,9 @@ impl AfbEvent {
         let status = unsafe { cglue::afb_api_new_event(apiv4, evt_uid.as_ptr(), &mut evt_id) };
         self._evtv4 = evt_id;
         self._apiv4 = apiv4;
+        if status >= 0 {
+            exit_track_event(self._uid, evt_id);
+        }
 
         status
     }
@@ -1621,7 +4185,7 @@ impl AfbEvent {
             return afb_error!(self._uid, "should register before usage");
         }
 
-        let status = unsafe { cglue::afb_req_subscribe(rqt.get_rqtv4(), self._evtv4) };
+        let status = unsafe { cglue::afb_req_subscribe(rqt.rqtv4_handle(), self._evtv4) };
         if status != 0 {
             afb_error!(self._uid, "fail to subscribe event")
         } else {
@@ -1635,7 +4199,7 @@ impl AfbEvent {
             return afb_error!(self._uid, "should register before usage");
         }
 
-        let status = unsafe { cglue::afb_req_unsubscribe(rqt.get_rqtv4(), self._evtv4) };
+        let status = unsafe { cglue::afb_req_unsubscribe(rqt.rqtv4_handle(), self._evtv4) };
         if status != 0 {
             afb_error!(self._uid, "fail to unsubscribe event")
         } else {
@@ -1733,11 +4297,26 @@ pub struct AfbGroup {
     prefix: &'static str,
     info: &'static str,
     permission: &'static AfbPermission,
-    verbosity: u32,
+    verbosity: AtomicU32,
+    verbosity_set: AtomicBool,
     separator: &'static str,
     verbs: Vec<*const AfbVerb>,
     events: Vec<*const AfbEvent>,
     evthandlers: Vec<*const AfbEvtHandler>,
+    limit: Option<&'static AfbLimit>,
+    interceptors: Vec<&'static dyn AfbInterceptor>,
+    groups: Vec<*const AfbGroup>,
+    // set once nested in another group, a group has at most one parent
+    nested: AtomicBool,
+}
+
+// settings a parent group passes down to its sub-groups at registration
+#[derive(Default)]
+struct AfbGroupInherit {
+    prefix: String,
+    verbosity: u32,
+    limit: Option<&'static AfbLimit>,
+    interceptors: Vec<&'static dyn AfbInterceptor>,
 }
 
 impl AfbGroup {
@@ -1745,13 +4324,18 @@ impl AfbGroup {
         let group_box = Box::new(AfbGroup {
             _uid: uid,
             info: "",
-            verbosity: 0,
+            verbosity: AtomicU32::new(0),
+            verbosity_set: AtomicBool::new(false),
             permission: AfbPermission::new(0),
             prefix: "",
             separator: "/",
             verbs: Vec::new(),
             evthandlers: Vec::new(),
             events: Vec::new(),
+            limit: None,
+            interceptors: Vec::new(),
+            groups: Vec::new(),
+            nested: AtomicBool::new(false),
         });
         Box::leak(group_box)
     }
@@ -1776,24 +4360,115 @@ impl AfbGroup {
         self
     }
 
+    /// Interceptor run around every verb of the group, after api interceptors.
+    pub fn add_interceptor(&mut self, interceptor: Box<dyn AfbInterceptor>) -> &mut Self {
+        self.interceptors.push(Box::leak(interceptor));
+        self
+    }
+
+    /// Limit shared by every verb of the group, checked after verb own limit.
+    pub fn set_limit(&mut self, limit: &'static AfbLimit) -> &mut Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn get_limit(&self) -> Option<&'static AfbLimit> {
+        self.limit
+    }
+
     pub fn set_verbosity(&mut self, value: i32) -> Result<&mut Self, AfbError> {
-        self.verbosity = verbosity_to_mask(value)?;
+        self.verbosity.store(verbosity_to_mask(value)?, Ordering::Relaxed);
+        self.verbosity_set.store(true, Ordering::Relaxed);
         Ok(self)
     }
 
+    pub fn get_verbosity(&self) -> u32 {
+        self.verbosity.load(Ordering::Relaxed)
+    }
+
+    /// Change group verbosity at runtime, group verbs, event handlers and sub-groups without
+    /// their own verbosity follow. Level 0 lets verbs fall back to libafb request logmask and
+    /// the group follow api changes again.
+    pub fn update_verbosity(&self, level: i32) -> Result<u32, AfbError> {
+        let mask = if level == 0 { 0 } else { verbosity_to_mask(level)? };
+        self.verbosity_set.store(level != 0, Ordering::Relaxed);
+        self.store_verbosity(mask);
+        Ok(mask)
+    }
+
+    // parent runtime change, an explicit group verbosity shields its whole subtree
+    fn follow_verbosity(&self, mask: u32) {
+        if !self.verbosity_set.load(Ordering::Relaxed) {
+            self.store_verbosity(mask);
+        }
+    }
+
+    fn store_verbosity(&self, mask: u32) {
+        self.verbosity.store(mask, Ordering::Relaxed);
+        for slot in &self.verbs {
+            unsafe { &*(*slot) }.follow_verbosity(mask);
+        }
+        for slot in &self.evthandlers {
+            unsafe { &*(*slot) }.follow_verbosity(mask);
+        }
+        for slot in &self.groups {
+            unsafe { &*(*slot) }.follow_verbosity(mask);
+        }
+    }
+
     pub fn add_verb(&mut self, verb: &AfbVerb) -> &mut Self {
         self.verbs.push(verb);
         self
     }
 
-    pub fn add_event(&mut self, event: &'static AfbEvent) -> &mut Self {
-        self.events.push(event);
-        self
+    pub fn add_event(&mut self, event: &'static AfbEvent) -> &mut Self {
+        self.events.push(event);
+        self
+    }
+
+    pub fn add_evt_handler(&mut self, handler: &AfbEvtHandler) -> &mut Self {
+        self.evthandlers.push(handler);
+        self
+    }
+
+    /// Nested group, its prefix is appended to parent one (`parent/child/verb`). Permissions,
+    /// verbosity, limit and interceptors are inherited from parent group. A group already nested
+    /// elsewhere, or one this group is reachable from, is rejected.
+    #[track_caller]
+    pub fn add_group(&mut self, group: &AfbGroup) -> Result<&mut Self, AfbError> {
+        let mut reachable = Vec::new();
+        AfbGroup::collect_groups(group, &mut reachable);
+        if reachable.iter().any(|slot| std::ptr::eq(*slot, self)) {
+            return afb_error!(self._uid, "nesting group:{} would create a cycle", group._uid);
+        }
+        if group.nested.swap(true, Ordering::AcqRel) {
+            return afb_error!(self._uid, "group:{} already nested in another group", group._uid);
+        }
+        self.groups.push(group);
+        Ok(self)
+    }
+
+    pub fn get_groups(&self) -> Vec<&'static AfbGroup> {
+        self.groups.iter().map(|slot| unsafe { &*(*slot) }).collect()
     }
 
-    pub fn add_evt_handler(&mut self, handler: &AfbEvtHandler) -> &mut Self {
-        self.evthandlers.push(handler);
-        self
+    // group followed by its sub-groups (depth first)
+    fn collect_groups(slot: *const AfbGroup, groups: &mut Vec<*const AfbGroup>) {
+        groups.push(slot);
+        for child in unsafe { &(*slot).groups } {
+            AfbGroup::collect_groups(*child, groups);
+        }
+    }
+
+    // group verbs followed by sub-group ones
+    fn get_all_verbs(&self) -> Vec<&'static AfbVerb> {
+        let mut slots = Vec::new();
+        AfbGroup::collect_groups(self, &mut slots);
+        slots
+            .iter()
+            .flat_map(|slot| unsafe { &(**slot).verbs })
+            .map(|verb| unsafe { &**verb })
+            .collect()
     }
 
     /// # Safety
@@ -1810,25 +4485,44 @@ impl AfbGroup {
         apiv4: cglue::afb_api_t,
         inherited_auth: *const AfbAuthV4,
     ) -> i32 {
+        unsafe { self.register_nested(apiv4, inherited_auth, &AfbGroupInherit::default()) }
+    }
+
+    #[track_caller]
+    unsafe fn register_nested(
+        &self,
+        apiv4: cglue::afb_api_t,
+        inherited_auth: *const AfbAuthV4,
+        parent: &AfbGroupInherit,
+    ) -> i32 {
+        let verb_prefix = if self.prefix.is_empty() {
+            parent.prefix.clone()
+        } else {
+            format!("{}{}{}", parent.prefix, self.prefix, self.separator)
+        };
+        let verbosity = self.verbosity.load(Ordering::Relaxed).max(parent.verbosity);
+        let limit = self.limit.or(parent.limit);
+        let mut interceptors = parent.interceptors.clone();
+        interceptors.extend(self.interceptors.iter());
+        let group_permission: *mut AfbAuthV4 = AfbPermisionV4::new(self.permission, inherited_auth);
+
         let mut status = 0;
         for slot in &self.verbs {
             let verb_ref = unsafe { &mut *(*slot as *mut AfbVerb) };
 
             // use group verbosity is higger than verb one
-            if self.verbosity > verb_ref.verbosity {
-                verb_ref.verbosity = self.verbosity;
-            }
-
-            // add prefix to verb name and rebuild a static str string
-            if !self.prefix.is_empty() {
-                verb_ref.name = Box::leak(
-                    (self.prefix.to_owned() + self.separator + verb_ref.name).into_boxed_str(),
-                );
+            verb_ref.inherit_verbosity(verbosity);
+            verb_ref.group_limit = limit;
+            verb_ref.group_interceptors = interceptors.clone();
+
+            // add prefix to verb name and rebuild a static str string (once, group may be registered again)
+            if !verb_prefix.is_empty() && !verb_ref.name.starts_with(&verb_prefix) {
+                verb_ref.name = to_static_str(verb_prefix.clone() + verb_ref.name);
+                for alias in verb_ref.aliases.iter_mut() {
+                    *alias = to_static_str(verb_prefix.clone() + alias);
+                }
             }
 
-            let group_permission: *mut AfbAuthV4 =
-                AfbPermisionV4::new(self.permission, inherited_auth);
-
             //call verb registration method
             status = unsafe { verb_ref.register(apiv4, group_permission) };
             if status < 0 {
@@ -1874,9 +4568,72 @@ impl AfbGroup {
                 };
             }
         }
+        if status >= 0 {
+            let inherit = AfbGroupInherit { prefix: verb_prefix, verbosity, limit, interceptors };
+            for slot in &self.groups {
+                let group_ref = unsafe { &*(*slot) };
+                status = unsafe { group_ref.register_nested(apiv4, group_permission, &inherit) };
+                if status < 0 {
+                    afb_log_msg!(
+                        Critical,
+                        apiv4,
+                        "Fail to register group={} parent={}",
+                        group_ref.get_uid(),
+                        self._uid
+                    );
+                    break;
+                };
+            }
+        }
         status
     }
 
+    /// Remove group verbs and event handlers from a running api, group may be registered again
+    /// later with `api.register_group(group)`. Removal is best effort: when libafb refuses some
+    /// items the error lists what is still registered and the group stays attached to the api.
+    #[track_caller]
+    pub fn unregister(&self, api: &AfbApi) -> Result<&Self, AfbError> {
+        let apiv4 = api.get_dynamic_apiv4()?;
+        let mut groups = api.groups.write().unwrap_or_else(PoisonError::into_inner);
+        if !groups.iter().any(|slot| std::ptr::eq(*slot, self)) {
+            return afb_error!(self._uid, "group not registered on api:{}", api.get_uid());
+        }
+
+        let mut remaining = Vec::new();
+        self.unregister_nested(apiv4, &mut remaining);
+        if !remaining.is_empty() {
+            return afb_error!(
+                self._uid,
+                "group partially removed, still registered:[{}]",
+                remaining.join(",")
+            );
+        }
+        groups.retain(|slot| !std::ptr::eq(*slot, self));
+        unregister_versions(api, &self.get_all_verbs());
+        Ok(self)
+    }
+
+    // collect verbs/handlers libafb failed to remove as "uid(status)"
+    fn unregister_nested(&self, apiv4: cglue::afb_api_t, remaining: &mut Vec<String>) {
+        for slot in &self.groups {
+            unsafe { &*(*slot) }.unregister_nested(apiv4, remaining);
+        }
+        for slot in &self.verbs {
+            let verb_ref = unsafe { &*(*slot) };
+            let status = unsafe { verb_ref.unregister(apiv4) };
+            if status < 0 {
+                remaining.push(format!("{}({})", verb_ref.get_name(), status));
+            }
+        }
+        for slot in &self.evthandlers {
+            let handler_ref = unsafe { &*(*slot) };
+            let status = unsafe { handler_ref.unregister(apiv4) };
+            if status < 0 {
+                remaining.push(format!("{}({})", handler_ref.get_uid(), status));
+            }
+        }
+    }
+
     // return object getter trait to prevent any malicious modification
     pub fn finalize(&mut self) -> Result<&AfbGroup, AfbError> {
         Ok(self)
@@ -1903,29 +4660,46 @@ pub unsafe extern "C" fn afb_async_rqt_callback(
     args: *const cglue::afb_data_t,
     rqtv4: cglue::afb_req_t,
 ) {
-    // extract api_ref from libafb
-    let api_ref = unsafe {
-        let apiv4 = cglue::afb_req_get_api(rqtv4);
-        let api_data = cglue::afb_api_get_userdata(apiv4);
-        &mut *(api_data as *mut AfbApi)
-    };
-
-    // retreive source verb object
-    let verb_ctx = unsafe { cglue::afb_req_get_vcbdata(rqtv4) };
-    let verb_ref = unsafe { &mut *(verb_ctx as *mut AfbVerb) };
+    let subcall_ref = unsafe { Box::from_raw(userdata as *mut AfbSubCall) };
+    subcall_rqt_reply(*subcall_ref, status, argc, args, rqtv4);
+}
 
+// run user callback of a request subcall with its response (or deadline error)
+unsafe fn subcall_rqt_reply(
+    subcall_ref: AfbSubCall,
+    status: i32,
+    argc: u32,
+    args: *const cglue::afb_data_t,
+    rqtv4: cglue::afb_req_t,
+) {
     // move const **array in something Rust may understand
-    let arguments = AfbRqtData::new(
-        unsafe { std::slice::from_raw_parts(args as *const cglue::afb_data_t, argc as usize) },
-        argc,
-        status,
-    );
-    // remap request on a valid Rust object
-    let request = AfbRequest::new(rqtv4, api_ref, verb_ref);
-
-    // extract verb+api object from libafb internals
-    let subcall_ref = unsafe { &mut *(userdata as *mut AfbSubCall) };
-    let result = (subcall_ref.rqt_cb.unwrap())(&request, &arguments, &subcall_ref.context);
+    let arguments = if argc == 0 {
+        AfbRqtData::new(&[], 0, status)
+    } else {
+        AfbRqtData::new(
+            unsafe { std::slice::from_raw_parts(args as *const cglue::afb_data_t, argc as usize) },
+            argc,
+            status,
+        )
+    };
+    // remap request on a valid Rust object, state moves from subcall so that the response handle
+    // remains the last one when checking for missing reply. Versioned requests carry a dispatcher
+    // as vcbdata, their verb is only known from the state.
+    let mut subcall_ref = subcall_ref;
+    let request = match subcall_ref.state.take() {
+        Some(state) => unsafe { AfbRequest::with_state(rqtv4, state) },
+        None => unsafe { AfbRequest::from_raw(cglue::afb_req_addref(rqtv4)) },
+    };
+    let verb_ref = request.verb;
+    let result = match afb_catch_panic(verb_ref._uid, || {
+        (subcall_ref.rqt_cb.unwrap())(&request, &arguments, &subcall_ref.context)
+    }) {
+        Ok(result) => result,
+        Err(error) => {
+            verb_ref.panicked(&request, error);
+            return;
+        },
+    };
 
     match result {
         Ok(()) => {},
@@ -1971,7 +4745,10 @@ pub unsafe extern "C" fn afb_async_api_callback(
 
     // extract verb+api object from libafb internals
     let subcall_ref = unsafe { &mut *(userdata as *mut AfbSubCall) };
-    let result = (subcall_ref.api_cb.unwrap())(api_ref, &mut arguments, &subcall_ref.context);
+    let result = afb_catch_panic(api_ref._uid, || {
+        (subcall_ref.api_cb.unwrap())(api_ref, &mut arguments, &subcall_ref.context)
+    })
+    .and_then(|result| result);
     match result {
         Ok(()) => {},
         Err(error) => {
@@ -1986,6 +4763,7 @@ pub fn afb_error_info(errcode: i32) -> &'static str {
         0 => "Success",
         -9 => "Invalid Scope",
         -11 => "No Reply",
+        -16 => "Request over limit",
         -17 => "Api/Verb already exist",
         -62 => "Watchdog expire",
         -110 => "Connection timeout",
@@ -2059,6 +4837,7 @@ impl<C: 'static> DoSubcallAsync<AfbApiV4, ApiCallback, C> for AfbSubCall {
             api_cb: Some(callback),
             rqt_cb: None,
             context: AfbCtxData::new(context),
+            state: None,
         }));
 
         unsafe {
@@ -2130,7 +4909,10 @@ impl<C: 'static> DoSubcallAsync<&AfbRequest, RqtCallback, C> for AfbSubCall {
         callback: RqtCallback,
         context: C,
     ) {
-        AfbSubCall::subcall_async((*rqt).get_rqtv4(), apiname, verbname, params, callback, context)
+        let state = rqt.state.clone();
+        AfbSubCall::subcall_rqt_async(
+            rqt._rqtv4, state, apiname, verbname, params, callback, context,
+        )
     }
 }
 
@@ -2143,7 +4925,7 @@ impl DoSubcallSync<&AfbRequest> for AfbSubCall {
         verbname: CString,
         params: &AfbParams,
     ) -> Result<AfbRqtData, AfbError> {
-        AfbSubCall::subcall_sync(rqt.get_rqtv4(), apiname, verbname, params)
+        AfbSubCall::subcall_rqt_sync(rqt._rqtv4, rqt.state.as_ref(), apiname, verbname, params)
     }
 }
 
@@ -2158,11 +4940,49 @@ impl<C: 'static> DoSubcallAsync<AfbRqtV4, RqtCallback, C> for AfbSubCall {
         callback: RqtCallback,
         context: C,
     ) {
-        let cbhandle = Box::into_raw(Box::new(AfbSubCall {
+        let state = AfbRqtState::lookup(rqtv4);
+        AfbSubCall::subcall_rqt_async(rqtv4, state, apiname, verbname, params, callback, context)
+    }
+}
+
+impl AfbSubCall {
+    // request subcall carries calling request state up to its response callback
+    #[track_caller]
+    fn subcall_rqt_async<C: 'static>(
+        rqtv4: AfbRqtV4,
+        state: Option<Arc<AfbRqtState>>,
+        apiname: CString,
+        verbname: CString,
+        params: &AfbParams,
+        callback: RqtCallback,
+        context: C,
+    ) {
+        // request deadline already expired, report it to callback as a subcall error
+        let remaining = match AfbRqtState::subcall_remaining(state.as_ref(), &apiname, &verbname) {
+            Ok(remaining) => remaining,
+            Err(error) => {
+                let request = match state {
+                    Some(state) => unsafe { AfbRequest::with_state(rqtv4, state) },
+                    None => unsafe { AfbRequest::from_raw(cglue::afb_req_addref(rqtv4)) },
+                };
+                afb_log_msg!(Notice, &request, &error);
+                let arguments = AfbRqtData::new(&[], 0, error.get_status());
+                if let Err(error) = callback(&request, &arguments, &AfbCtxData::new(context)) {
+                    request.reply(error, -100);
+                }
+                return;
+            },
+        };
+        let subcall = Box::new(AfbSubCall {
             api_cb: None,
             rqt_cb: Some(callback),
             context: AfbCtxData::new(context),
-        }));
+            state,
+        });
+        if let Some(remaining) = remaining {
+            AfbSubCallRace::start(rqtv4, apiname, verbname, params, subcall, remaining);
+            return;
+        }
         unsafe {
             cglue::afb_req_subcall(
                 rqtv4,
@@ -2172,9 +4992,118 @@ impl<C: 'static> DoSubcallAsync<AfbRqtV4, RqtCallback, C> for AfbSubCall {
                 params.arguments.as_slice().as_ptr(),
                 cglue::afb_req_subcall_flags_afb_req_subcall_catch_events as i32,
                 Some(afb_async_rqt_callback),
-                cbhandle as *mut std::ffi::c_void,
+                Box::into_raw(subcall) as *mut std::ffi::c_void,
+            )
+        };
+    }
+}
+
+// callback subcall racing calling request deadline, first one to fire takes the user callback
+struct AfbSubCallRace {
+    rqtv4: cglue::afb_req_t,
+    subcall: Mutex<Option<Box<AfbSubCall>>>,
+    jobid: AtomicI32,
+}
+
+// libafb runs response and deadline callbacks on any binder thread, only one of them takes the
+// subcall handle under the mutex
+unsafe impl Send for AfbSubCallRace {}
+unsafe impl Sync for AfbSubCallRace {}
+
+impl AfbSubCallRace {
+    fn start(
+        rqtv4: cglue::afb_req_t,
+        apiname: CString,
+        verbname: CString,
+        params: &AfbParams,
+        subcall: Box<AfbSubCall>,
+        remaining: Duration,
+    ) {
+        let race = Arc::new(AfbSubCallRace {
+            rqtv4: unsafe { cglue::afb_req_addref(rqtv4) },
+            subcall: Mutex::new(Some(subcall)),
+            jobid: AtomicI32::new(0),
+        });
+        unsafe {
+            cglue::afb_req_subcall(
+                rqtv4,
+                apiname.into_raw(),
+                verbname.into_raw(),
+                params.arguments.len() as u32,
+                params.arguments.as_slice().as_ptr(),
+                cglue::afb_req_subcall_flags_afb_req_subcall_catch_events as i32,
+                Some(afb_race_rqt_callback),
+                Arc::into_raw(race.clone()) as *mut std::ffi::c_void,
+            )
+        };
+        let handle = Arc::into_raw(race.clone());
+        let jobid = unsafe {
+            cglue::afb_job_post(
+                remaining.as_millis() as i64,
+                0,
+                Some(afb_race_deadline_cb),
+                handle as *mut std::ffi::c_void,
+                std::ptr::null_mut(),
             )
         };
+        if jobid <= 0 {
+            drop(unsafe { Arc::from_raw(handle) });
+            return;
+        }
+        race.jobid.store(jobid, Ordering::Release);
+        // response received before job id was known, deadline is useless
+        if race.subcall.lock().unwrap_or_else(PoisonError::into_inner).is_none() {
+            race.abort();
+        }
+    }
+
+    fn take(&self) -> Option<Box<AfbSubCall>> {
+        self.subcall.lock().unwrap_or_else(PoisonError::into_inner).take()
+    }
+
+    fn abort(&self) {
+        let jobid = self.jobid.swap(0, Ordering::AcqRel);
+        if jobid > 0 {
+            unsafe { cglue::afb_job_abort(jobid) };
+        }
+    }
+}
+
+impl Drop for AfbSubCallRace {
+    fn drop(&mut self) {
+        unsafe { cglue::afb_req_unref(self.rqtv4) };
+    }
+}
+
+/// # Safety
+/// `userdata` comes from `Arc::into_raw` on a subcall race, pointers are provided by libafb.
+#[no_mangle]
+pub unsafe extern "C" fn afb_race_rqt_callback(
+    userdata: *mut std::os::raw::c_void,
+    status: i32,
+    argc: u32,
+    args: *const cglue::afb_data_t,
+    rqtv4: cglue::afb_req_t,
+) {
+    let race = Arc::from_raw(userdata as *const AfbSubCallRace);
+    // deadline already reported to user callback, late response is dropped
+    if let Some(subcall) = race.take() {
+        race.abort();
+        subcall_rqt_reply(*subcall, status, argc, args, rqtv4);
+    }
+}
+
+/// # Safety
+/// `userdata` comes from `Arc::into_raw` on a subcall race.
+#[no_mangle]
+pub unsafe extern "C" fn afb_race_deadline_cb(signal: i32, userdata: *mut std::os::raw::c_void) {
+    let race = Arc::from_raw(userdata as *const AfbSubCallRace);
+    // aborted once response was received
+    if signal != 0 {
+        return;
+    }
+    if let Some(subcall) = race.take() {
+        subcall_rqt_reply(*subcall, -62, 0, std::ptr::null(), race.rqtv4);
     }
 }
 impl DoSubcallSync<AfbRqtV4> for AfbSubCall {
@@ -2186,6 +5115,23 @@ impl DoSubcallSync<AfbRqtV4> for AfbSubCall {
         verbname: CString,
         params: &AfbParams,
     ) -> Result<AfbRqtData, AfbError> {
+        let state = AfbRqtState::lookup(rqtv4);
+        AfbSubCall::subcall_rqt_sync(rqtv4, state.as_ref(), apiname, verbname, params)
+    }
+}
+
+impl AfbSubCall {
+    #[track_caller]
+    fn subcall_rqt_sync(
+        rqtv4: AfbRqtV4,
+        state: Option<&Arc<AfbRqtState>>,
+        apiname: CString,
+        verbname: CString,
+        params: &AfbParams,
+    ) -> Result<AfbRqtData, AfbError> {
+        if let Some(remaining) = AfbRqtState::subcall_remaining(state, &apiname, &verbname)? {
+            return AfbSubCallWait::call(rqtv4, apiname, verbname, params, remaining);
+        }
         let mut status = 0_i32;
         let mut nreplies = MAX_CALL_ARGS;
         let replies = [0 as cglue::afb_data_t; MAX_CALL_ARGS as usize];
@@ -2223,10 +5169,202 @@ impl DoSubcallSync<AfbRqtV4> for AfbSubCall {
     }
 }
 
+// sync subcall bounded by calling request deadline: binder thread keeps running libafb jobs in
+// a scheduler section, left by the response or by the deadline job, whichever comes first
+struct AfbSubCallWait {
+    rqtv4: cglue::afb_req_t,
+    apiname: CString,
+    verbname: CString,
+    // only read from the enter callback, while `call` borrows params
+    params: *const AfbParams,
+    remaining: Duration,
+    state: Mutex<AfbSubCallWaitState>,
+}
+
+struct AfbSubCallWaitState {
+    lock: *mut cglue::afb_sched_lock,
+    result: Option<Result<AfbRqtData, AfbError>>,
+    jobid: i32,
+}
+
+// response and deadline callbacks run on any binder thread, state is behind a mutex
+unsafe impl Send for AfbSubCallWait {}
+unsafe impl Sync for AfbSubCallWait {}
+
+impl AfbSubCallWait {
+    #[track_caller]
+    fn call(
+        rqtv4: cglue::afb_req_t,
+        apiname: CString,
+        verbname: CString,
+        params: &AfbParams,
+        remaining: Duration,
+    ) -> Result<AfbRqtData, AfbError> {
+        let wait = Arc::new(AfbSubCallWait {
+            rqtv4,
+            apiname,
+            verbname,
+            params,
+            remaining,
+            state: Mutex::new(AfbSubCallWaitState {
+                lock: std::ptr::null_mut(),
+                result: None,
+                jobid: 0,
+            }),
+        });
+        let rc = unsafe {
+            cglue::afb_sched_enter(
+                std::ptr::null_mut(),
+                0,
+                Some(afb_wait_enter_cb),
+                Arc::as_ptr(&wait) as *mut std::ffi::c_void,
+            )
+        };
+        let result = wait.lock().result.take();
+        let datas = match result {
+            Some(Ok(datas)) if rc >= 0 => datas,
+            Some(Err(error)) => return Err(error),
+            _ => {
+                return Err(AfbError::new(
+                    "rqt-subcalls",
+                    -62,
+                    format!(
+                        "api:{:?} verb:{:?} subcall aborted rc:{}",
+                        wait.apiname, wait.verbname, rc
+                    ),
+                ))
+            },
+        };
+        let status = datas.get_status();
+        if status < 0 {
+            let error = match datas.get::<JsoncObj>(0) {
+                Ok(jerror) => jerror.to_string(),
+                Err(_) => format!(
+                    "api:{:?} verb:{:?} status:{}({})",
+                    wait.apiname,
+                    wait.verbname,
+                    status,
+                    afb_error_info(status)
+                ),
+            };
+            return Err(AfbError::new("rqt-subcalls", status, error));
+        }
+        Ok(datas)
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, AfbSubCallWaitState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // first completion wins, scheduler section is left once
+    fn complete(&self, result: Result<AfbRqtData, AfbError>) {
+        let mut state = self.lock();
+        if state.lock.is_null() {
+            return;
+        }
+        state.result = Some(result);
+        let jobid = std::mem::take(&mut state.jobid);
+        if jobid > 0 {
+            unsafe { cglue::afb_job_abort(jobid) };
+        }
+        let lock = std::mem::replace(&mut state.lock, std::ptr::null_mut());
+        unsafe { cglue::afb_sched_leave(lock) };
+    }
+}
+
+/// # Safety
+/// `closure` points to the `AfbSubCallWait` kept alive by `AfbSubCallWait::call`.
+#[no_mangle]
+pub unsafe extern "C" fn afb_wait_enter_cb(
+    signal: i32,
+    closure: *mut std::os::raw::c_void,
+    lock: *mut cglue::afb_sched_lock,
+) {
+    if signal != 0 {
+        return;
+    }
+    let wait = closure as *const AfbSubCallWait;
+    let params = &*(*wait).params;
+    (*wait).lock().lock = lock;
+
+    // response and deadline callbacks own one reference each
+    Arc::increment_strong_count(wait);
+    cglue::afb_req_subcall(
+        (*wait).rqtv4,
+        (*wait).apiname.as_ptr(),
+        (*wait).verbname.as_ptr(),
+        params.arguments.len() as u32,
+        params.arguments.as_slice().as_ptr(),
+        cglue::afb_req_subcall_flags_afb_req_subcall_catch_events as i32,
+        Some(afb_wait_rqt_callback),
+        closure,
+    );
+
+    Arc::increment_strong_count(wait);
+    let jobid = cglue::afb_job_post(
+        (*wait).remaining.as_millis() as i64,
+        0,
+        Some(afb_wait_deadline_cb),
+        closure,
+        std::ptr::null_mut(),
+    );
+    if jobid <= 0 {
+        drop(Arc::from_raw(wait));
+        return;
+    }
+    let mut state = (*wait).lock();
+    if state.lock.is_null() {
+        // response already received
+        drop(state);
+        cglue::afb_job_abort(jobid);
+    } else {
+        state.jobid = jobid;
+    }
+}
+
+/// # Safety
+/// `userdata` comes from an `AfbSubCallWait` reference, pointers are provided by libafb.
+#[no_mangle]
+pub unsafe extern "C" fn afb_wait_rqt_callback(
+    userdata: *mut std::os::raw::c_void,
+    status: i32,
+    argc: u32,
+    args: *const cglue::afb_data_t,
+    _rqtv4: cglue::afb_req_t,
+) {
+    let wait = Arc::from_raw(userdata as *const AfbSubCallWait);
+    // response data belongs to libafb, keep our own references
+    let datas = if argc == 0 {
+        AfbRqtData::new(&[], 0, status)
+    } else {
+        let args = std::slice::from_raw_parts(args as *const cglue::afb_data_t, argc as usize);
+        AfbRqtData::new_owned(args, argc, status)
+    };
+    wait.complete(Ok(datas));
+}
+
+/// # Safety
+/// `userdata` comes from an `AfbSubCallWait` reference.
+#[no_mangle]
+pub unsafe extern "C" fn afb_wait_deadline_cb(signal: i32, userdata: *mut std::os::raw::c_void) {
+    let wait = Arc::from_raw(userdata as *const AfbSubCallWait);
+    // aborted once response was received
+    if signal != 0 {
+        return;
+    }
+    let error = format!(
+        "request deadline expired during subcall api:{:?} verb:{:?}",
+        wait.apiname, wait.verbname
+    );
+    wait.complete(Err(AfbError::new("rqt-timeout", -62, error)));
+}
+
 pub struct AfbSubCall {
     context: AfbCtxData,
     api_cb: Option<ApiCallback>,
     rqt_cb: Option<RqtCallback>,
+    // calling request state, handed to the request rebuilt for the response callback
+    state: Option<Arc<AfbRqtState>>,
 }
 
 impl AfbSubCall {
@@ -2283,4 +5421,21 @@ impl AfbSubCall {
         let _: () = AfbSubCall::subcall_async(handle, apistr, verbstr, &params, callback, context);
         Ok(())
     }
+
+    /// Subcall returning a future, several calls may be awaited together with join/select.
+    #[track_caller]
+    pub fn call<H, T>(handle: H, apiname: &str, verbname: &str, args: T) -> AfbSubCallFuture
+    where
+        AfbParams: ConvertResponse<T>,
+        AfbSubCall: DoSubcallFuture<H>,
+    {
+        let params = match AfbParams::convert(args) {
+            Err(error) => return AfbSubCallFuture::from_error(error),
+            Ok(data) => data,
+        };
+
+        let apistr = CString::new(apiname).expect("Invalid apiname");
+        let verbstr = CString::new(verbname).expect("Invalid verbname");
+        AfbSubCall::subcall_future(handle, apistr, verbstr, &params)
+    }
 }
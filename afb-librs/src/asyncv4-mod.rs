@@ -0,0 +1,596 @@
+/*
+ * Copyright (C) 2015-2023 IoT.bzh Company
+ * Author: Fulup Ar Foll <fulup@iot.bzh>
+ *
+ * $RP_BEGIN_LICENSE$
+ * Commercial License Usage
+ *  Licensees holding valid commercial IoT.bzh licenses may use this file in
+ *  accordance with the commercial license agreement provided with the
+ *  Software or, alternatively, in accordance with the terms contained in
+ *  a written agreement between you and The IoT.bzh Company. For licensing terms
+ *  and conditions see https://www.iot.bzh/terms-conditions. For further
+ *  information use the contact form at https://www.iot.bzh/contact.
+ *
+ * GNU General Public License Usage
+ *  Alternatively, this file may be used under the terms of the GNU General
+ *  Public license version 3. This license is as published by the Free Software
+ *  Foundation and appearing in the file LICENSE.GPLv3 included in the packaging
+ *  of this file. Please review the following information to ensure the GNU
+ *  General Public License requirements will be met
+ *  https://www.gnu.org/licenses/gpl-3.0.html.
+ * $RP_END_LICENSE$
+ */
+
+// Minimal future executor running on top of libafb job loop. Each spawned future
+// is wrapped into an AfbTask; waking the task posts a job that polls it again.
+
+use crate::prelude::*;
+use std::ffi::CString;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll, Wake, Waker};
+use std::time::Duration;
+
+pub type AfbFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+struct AfbTask {
+    uid: &'static str,
+    future: Mutex<Option<AfbFuture>>,
+    queued: AtomicBool,
+}
+
+impl AfbTask {
+    fn poll(self: &Arc<Self>) {
+        let waker = Waker::from(self.clone());
+        let mut context = Context::from_waker(&waker);
+        let mut slot = self.future.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(future) = slot.as_mut() {
+            match afb_catch_panic(self.uid, || future.as_mut().poll(&mut context)) {
+                Ok(Poll::Ready(())) => *slot = None,
+                Ok(Poll::Pending) => {},
+                // drop panicked future, captured requests answer with their no reply status
+                Err(error) => {
+                    afb_log_msg!(Critical, None, &error);
+                    *slot = None;
+                },
+            }
+        }
+    }
+
+    fn schedule(self: Arc<Self>) {
+        // task already waiting for its job to be processed
+        if self.queued.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let uid = self.uid;
+        let handle = Arc::into_raw(self);
+        let jobid = unsafe {
+            cglue::afb_job_post(
+                0,
+                0,
+                Some(api_async_task_cb),
+                handle as *mut std::ffi::c_void,
+                std::ptr::null_mut(),
+            )
+        };
+        if jobid <= 0 {
+            let task = unsafe { Arc::from_raw(handle) };
+            task.queued.store(false, Ordering::Release);
+            afb_log_msg!(Critical, None, "async task:{} fail to post job", uid);
+        }
+    }
+}
+
+impl Wake for AfbTask {
+    fn wake(self: Arc<Self>) {
+        self.schedule();
+    }
+}
+
+/// Job callback polling an async task when its waker was triggered.
+///
+/// # Safety
+/// `userdata` must come from `Arc::into_raw` on an `AfbTask`; the reference is consumed here.
+#[no_mangle]
+pub unsafe extern "C" fn api_async_task_cb(signal: i32, userdata: *mut std::os::raw::c_void) {
+    let task = Arc::from_raw(userdata as *const AfbTask);
+    task.queued.store(false, Ordering::Release);
+
+    // dropping the future runs user destructors, nothing unwinds into libafb
+    let result = afb_catch_panic(task.uid, || {
+        // job was aborted, drop the future and everything it captured
+        if signal != 0 {
+            afb_log_msg!(Error, None, "async task:{} aborted signal={}", task.uid, signal);
+            *task.future.lock().unwrap_or_else(PoisonError::into_inner) = None;
+        } else {
+            task.poll();
+        }
+    });
+    if let Err(error) = result {
+        afb_log_msg!(Critical, None, &error);
+    }
+}
+
+/// Run a future on libafb job loop. First poll happens immediately within the
+/// calling thread, further ones are posted as jobs each time the future is woken,
+/// possibly on another binder thread: future must be `Send`.
+pub fn afb_spawn<F>(uid: &'static str, future: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let task = Arc::new(AfbTask {
+        uid,
+        future: Mutex::new(Some(Box::pin(future))),
+        queued: AtomicBool::new(false),
+    });
+    task.poll();
+}
+
+struct AfbDelayState {
+    expired: bool,
+    waker: Option<Waker>,
+}
+
+/// Future completing after `delay_ms`, timer is a delayed libafb job.
+pub struct AfbDelay {
+    delay_ms: i64,
+    state: Option<Arc<Mutex<AfbDelayState>>>,
+}
+
+impl AfbDelay {
+    pub fn new(delay_ms: u32) -> Self {
+        AfbDelay { delay_ms: delay_ms as i64, state: None }
+    }
+}
+
+/// # Safety
+/// `userdata` must come from `Arc::into_raw` on the delay shared state.
+#[no_mangle]
+pub unsafe extern "C" fn api_async_delay_cb(_signal: i32, userdata: *mut std::os::raw::c_void) {
+    let state = Arc::from_raw(userdata as *const Mutex<AfbDelayState>);
+    let waker = {
+        let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.expired = true;
+        state.waker.take()
+    };
+    // foreign executor wakers run their own code
+    if let Some(waker) = waker {
+        if let Err(error) = afb_catch_panic("async-delay", || waker.wake()) {
+            afb_log_msg!(Critical, None, &error);
+        }
+    }
+}
+
+impl Future for AfbDelay {
+    type Output = Result<(), AfbError>;
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Self::Output> {
+        match &self.state {
+            Some(state) => {
+                let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+                if state.expired {
+                    Poll::Ready(Ok(()))
+                } else {
+                    state.waker = Some(context.waker().clone());
+                    Poll::Pending
+                }
+            },
+            None => {
+                let state = Arc::new(Mutex::new(AfbDelayState {
+                    expired: false,
+                    waker: Some(context.waker().clone()),
+                }));
+                let handle = Arc::into_raw(state.clone());
+                let jobid = unsafe {
+                    cglue::afb_job_post(
+                        self.delay_ms,
+                        0,
+                        Some(api_async_delay_cb),
+                        handle as *mut std::ffi::c_void,
+                        std::ptr::null_mut(),
+                    )
+                };
+                if jobid <= 0 {
+                    let _ = unsafe { Arc::from_raw(handle) };
+                    return Poll::Ready(afb_error!("afb-async-delay", "fail to post delay job"));
+                }
+                self.state = Some(state);
+                Poll::Pending
+            },
+        }
+    }
+}
+
+struct AfbSubCallState {
+    uid: String,
+    // set once by subcall response or by calling request deadline, whichever comes first
+    done: bool,
+    result: Option<Result<AfbRqtData, AfbError>>,
+    waker: Option<Waker>,
+    // deadline job, aborted when response comes first
+    jobid: i32,
+}
+
+/// Future returned by `AfbSubCall::call`, resolves when subcall response is received.
+pub struct AfbSubCallFuture {
+    state: Arc<Mutex<AfbSubCallState>>,
+}
+
+impl AfbSubCallFuture {
+    fn new(uid: String) -> Self {
+        AfbSubCallFuture {
+            state: Arc::new(Mutex::new(AfbSubCallState {
+                uid,
+                done: false,
+                result: None,
+                waker: None,
+                jobid: 0,
+            })),
+        }
+    }
+
+    #[track_caller]
+    pub(crate) fn from_error(error: AfbError) -> Self {
+        let future = AfbSubCallFuture::new(error.get_uid());
+        let mut state = future.state.lock().unwrap_or_else(PoisonError::into_inner);
+        state.done = true;
+        state.result = Some(Err(error));
+        drop(state);
+        future
+    }
+
+    // resolve with -62 when calling request deadline expires before subcall response
+    fn set_deadline(&self, remaining: Duration) {
+        // response callback cannot complete meanwhile, it finds the job id
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        if state.done {
+            return;
+        }
+        let handle = Arc::into_raw(self.state.clone());
+        let jobid = unsafe {
+            cglue::afb_job_post(
+                remaining.as_millis() as i64,
+                0,
+                Some(api_async_deadline_cb),
+                handle as *mut std::ffi::c_void,
+                std::ptr::null_mut(),
+            )
+        };
+        if jobid <= 0 {
+            let _ = unsafe { Arc::from_raw(handle) };
+        } else {
+            state.jobid = jobid;
+        }
+    }
+
+    fn get_userdata(&self) -> *mut std::ffi::c_void {
+        Arc::into_raw(self.state.clone()) as *mut std::ffi::c_void
+    }
+}
+
+impl Future for AfbSubCallFuture {
+    type Output = Result<AfbRqtData, AfbError>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(context.waker().clone());
+                Poll::Pending
+            },
+        }
+    }
+}
+
+/// # Safety
+/// `userdata` must come from `Arc::into_raw` on a subcall future state.
+#[no_mangle]
+pub unsafe extern "C" fn api_async_deadline_cb(signal: i32, userdata: *mut std::os::raw::c_void) {
+    let state = Arc::from_raw(userdata as *const Mutex<AfbSubCallState>);
+    // aborted once response was received
+    if signal != 0 {
+        return;
+    }
+    let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+    if state.done {
+        return;
+    }
+    state.done = true;
+    state.jobid = 0;
+    let error = format!("{} calling request deadline expired", state.uid);
+    state.result = Some(Err(AfbError::new("future-subcalls", -62, error)));
+    if let Some(waker) = state.waker.take() {
+        waker.wake();
+    }
+}
+
+unsafe fn subcall_future_complete(
+    userdata: *mut std::os::raw::c_void,
+    status: i32,
+    argc: u32,
+    args: *const cglue::afb_data_t,
+) {
+    let state = Arc::from_raw(userdata as *const Mutex<AfbSubCallState>);
+    let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+    // deadline already resolved the future
+    if state.done {
+        return;
+    }
+    state.done = true;
+    let jobid = std::mem::take(&mut state.jobid);
+    if jobid > 0 {
+        cglue::afb_job_abort(jobid);
+    }
+    let args = std::slice::from_raw_parts(args as *const cglue::afb_data_t, argc as usize);
+
+    let result = if status < 0 {
+        let replies = AfbRqtData::new(args, argc, status);
+        let error = match replies.get::<JsoncObj>(0) {
+            Ok(jerror) => jerror.to_string(),
+            Err(_) => format!("{} status:{}({})", state.uid, status, afb_error_info(status)),
+        };
+        Err(AfbError::new("future-subcalls", status, error))
+    } else {
+        // libafb releases replies when callback returns, keep a reference for the future
+        Ok(AfbRqtData::new_owned(args, argc, status))
+    };
+    state.result = Some(result);
+    if let Some(waker) = state.waker.take() {
+        waker.wake();
+    }
+}
+
+/// # Safety
+/// `userdata` is the future state leaked by `AfbSubCall::call`; `args` holds `argc` items.
+#[no_mangle]
+pub unsafe extern "C" fn afb_future_api_callback(
+    userdata: *mut std::os::raw::c_void,
+    status: i32,
+    argc: u32,
+    args: *const cglue::afb_data_t,
+    _apiv4: cglue::afb_api_t,
+) {
+    subcall_future_complete(userdata, status, argc, args);
+}
+
+/// # Safety
+/// `userdata` is the future state leaked by `AfbSubCall::call`; `args` holds `argc` items.
+#[no_mangle]
+pub unsafe extern "C" fn afb_future_rqt_callback(
+    userdata: *mut std::os::raw::c_void,
+    status: i32,
+    argc: u32,
+    args: *const cglue::afb_data_t,
+    _rqtv4: cglue::afb_req_t,
+) {
+    subcall_future_complete(userdata, status, argc, args);
+}
+
+pub trait DoSubcallFuture<H> {
+    fn subcall_future(
+        handle: H,
+        apiname: CString,
+        verbname: CString,
+        params: &AfbParams,
+    ) -> AfbSubCallFuture;
+}
+
+impl DoSubcallFuture<&AfbApi> for AfbSubCall {
+    fn subcall_future(
+        api: &AfbApi,
+        apiname: CString,
+        verbname: CString,
+        params: &AfbParams,
+    ) -> AfbSubCallFuture {
+        AfbSubCall::subcall_future(api.get_apiv4(), apiname, verbname, params)
+    }
+}
+
+impl DoSubcallFuture<AfbApiV4> for AfbSubCall {
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    fn subcall_future(
+        apiv4: AfbApiV4,
+        apiname: CString,
+        verbname: CString,
+        params: &AfbParams,
+    ) -> AfbSubCallFuture {
+        let future = AfbSubCallFuture::new(format!("api:{:?} verb:{:?}", apiname, verbname));
+        unsafe {
+            cglue::afb_api_call(
+                apiv4,
+                apiname.into_raw(),
+                verbname.into_raw(),
+                params.arguments.len() as u32,
+                params.arguments.as_slice().as_ptr(),
+                Some(afb_future_api_callback),
+                future.get_userdata(),
+            )
+        };
+        future
+    }
+}
+
+impl DoSubcallFuture<&AfbRequest> for AfbSubCall {
+    fn subcall_future(
+        rqt: &AfbRequest,
+        apiname: CString,
+        verbname: CString,
+        params: &AfbParams,
+    ) -> AfbSubCallFuture {
+        subcall_rqt_future(rqt.rqtv4_handle(), rqt.state.as_ref(), apiname, verbname, params)
+    }
+}
+
+impl DoSubcallFuture<AfbRqtV4> for AfbSubCall {
+    #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    fn subcall_future(
+        rqtv4: AfbRqtV4,
+        apiname: CString,
+        verbname: CString,
+        params: &AfbParams,
+    ) -> AfbSubCallFuture {
+        let state = AfbRqtState::lookup(rqtv4);
+        subcall_rqt_future(rqtv4, state.as_ref(), apiname, verbname, params)
+    }
+}
+
+// future deadline follows calling request deadline
+fn subcall_rqt_future(
+    rqtv4: AfbRqtV4,
+    state: Option<&Arc<AfbRqtState>>,
+    apiname: CString,
+    verbname: CString,
+    params: &AfbParams,
+) -> AfbSubCallFuture {
+    let remaining = match AfbRqtState::subcall_remaining(state, &apiname, &verbname) {
+        Ok(remaining) => remaining,
+        Err(error) => return AfbSubCallFuture::from_error(error),
+    };
+    let future = AfbSubCallFuture::new(format!("api:{:?} verb:{:?}", apiname, verbname));
+    unsafe {
+        cglue::afb_req_subcall(
+            rqtv4,
+            apiname.into_raw(),
+            verbname.into_raw(),
+            params.arguments.len() as u32,
+            params.arguments.as_slice().as_ptr(),
+            cglue::afb_req_subcall_flags_afb_req_subcall_catch_events as i32,
+            Some(afb_future_rqt_callback),
+            future.get_userdata(),
+        )
+    };
+    if let Some(remaining) = remaining {
+        future.set_deadline(remaining);
+    }
+    future
+}
+
+// libafb may answer permission checks later (e.g. remote authority), libafb status is shared
+// with the sync check or the future through an Arc owned by the callback
+pub(crate) struct AfbPermissionState {
+    permission: CString,
+    result: Mutex<(Option<i32>, Option<Waker>)>,
+}
+
+impl AfbPermissionState {
+    #[track_caller]
+    pub(crate) fn check(
+        request: &AfbRequest,
+        permission: &str,
+    ) -> Result<Arc<AfbPermissionState>, AfbError> {
+        let permission = match CString::new(permission) {
+            Ok(permission) => permission,
+            Err(_) => return afb_error!("rqt-permission", "invalid permission:{}", permission),
+        };
+        let state = Arc::new(AfbPermissionState { permission, result: Mutex::new((None, None)) });
+        unsafe {
+            cglue::afb_req_check_permission(
+                request.rqtv4_handle(),
+                state.permission.as_ptr(),
+                Some(api_permission_cb),
+                Arc::into_raw(state.clone()) as *mut std::ffi::c_void,
+            )
+        };
+        Ok(state)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, (Option<i32>, Option<Waker>)> {
+        self.result.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // libafb answers local permissions before check returns, never blocks for remote ones
+    #[track_caller]
+    pub(crate) fn answered(&self) -> Result<bool, AfbError> {
+        match self.lock().0 {
+            Some(status) => self.granted(status),
+            None => Err(AfbError::new(
+                "rqt-permission-pending",
+                -11,
+                format!(
+                    "permission:{:?} answered asynchronously, use has_permission_async",
+                    self.permission
+                ),
+            )),
+        }
+    }
+
+    // negative libafb status is a check failure, not a denial
+    #[track_caller]
+    fn granted(&self, status: i32) -> Result<bool, AfbError> {
+        if status < 0 {
+            Err(AfbError::new(
+                "rqt-permission",
+                status,
+                format!("permission:{:?} check failed status:{}", self.permission, status),
+            ))
+        } else {
+            Ok(status > 0)
+        }
+    }
+}
+
+/// Future returned by `AfbRequest::has_permission_async`, resolves with permission check result.
+pub struct AfbPermissionFuture {
+    state: Result<Arc<AfbPermissionState>, Option<AfbError>>,
+}
+
+impl Future for AfbPermissionFuture {
+    type Output = Result<bool, AfbError>;
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context<'_>) -> Poll<Self::Output> {
+        let state = match &mut self.state {
+            Ok(state) => state,
+            // early error was already returned, a future is not polled again once ready
+            Err(error) => match error.take() {
+                Some(error) => return Poll::Ready(Err(error)),
+                None => {
+                    return Poll::Ready(afb_error!(
+                        "rqt-permission",
+                        "permission future polled after completion"
+                    ))
+                },
+            },
+        };
+        let mut result = state.lock();
+        match result.0 {
+            Some(status) => Poll::Ready(state.granted(status)),
+            None => {
+                result.1 = Some(context.waker().clone());
+                Poll::Pending
+            },
+        }
+    }
+}
+
+impl AfbRequest {
+    /// Async permission check for async verbs, does not block libafb job loop while a remote
+    /// authority answers.
+    #[track_caller]
+    pub fn has_permission_async(&self, permission: &str) -> AfbPermissionFuture {
+        AfbPermissionFuture { state: AfbPermissionState::check(self, permission).map_err(Some) }
+    }
+}
+
+/// # Safety
+/// `closure` must come from `Arc::into_raw` on a permission check state.
+#[no_mangle]
+pub unsafe extern "C" fn api_permission_cb(
+    closure: *mut std::os::raw::c_void,
+    status: i32,
+    _rqtv4: cglue::afb_req_t,
+) {
+    let state = Arc::from_raw(closure as *const AfbPermissionState);
+    let waker = {
+        let mut result = state.lock();
+        result.0 = Some(status);
+        result.1.take()
+    };
+    if let Some(waker) = waker {
+        if let Err(error) = afb_catch_panic("rqt-permission", || waker.wake()) {
+            afb_log_msg!(Critical, None, &error);
+        }
+    }
+}
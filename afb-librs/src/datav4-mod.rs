@@ -72,6 +72,25 @@ macro_rules! AfbDataConverter {
             use std::any::Any;
             pub static mut CONVERTER_BOX: ConverterBox = ConverterBox(None);
 
+            // typed verb sample is the Default value when the type has one
+            pub struct SampleProbe<T>(pub std::marker::PhantomData<T>);
+            pub trait SampleDefault {
+                fn sample(&self) -> Option<String>;
+            }
+            impl<T: Default + serde::Serialize> SampleDefault for SampleProbe<T> {
+                fn sample(&self) -> Option<String> {
+                    serde_json::to_string(&T::default()).ok()
+                }
+            }
+            pub trait SampleNone {
+                fn sample(&self) -> Option<String>;
+            }
+            impl<T> SampleNone for &SampleProbe<T> {
+                fn sample(&self) -> Option<String> {
+                    None
+                }
+            }
+
             #[track_caller]
             pub fn encode(cbuffer: *mut std::ffi::c_void) -> Result<String, AfbError> {
                 let data = unsafe { &mut *(cbuffer as *mut $datat) };
@@ -121,6 +140,14 @@ macro_rules! AfbDataConverter {
             }
         }
 
+        impl afbv4::apiv4::AfbVerbSample for $datat {
+            fn verb_sample() -> Option<String> {
+                use $uid::{SampleDefault, SampleNone};
+                let probe = &$uid::SampleProbe::<$datat>(std::marker::PhantomData);
+                probe.sample()
+            }
+        }
+
         impl ConvertQuery<&'static $datat> for afbv4::datav4::AfbRqtData {
             #[track_caller]
             fn import(&self, index: usize) -> Result<&'static $datat, afbv4::utilv4::AfbError> {
@@ -680,12 +707,31 @@ pub struct AfbRqtData {
     count: u32,
     status: i32,
     argsv4: Vec<AfbDataV4>,
+    // holds its own data references, released on drop
+    owned: bool,
 }
 
+// data handles are libafb refcounted objects, safe to move between binder threads
+unsafe impl Send for AfbRqtData {}
+
 impl AfbRqtData {
+    /// Borrow data owned by libafb for the duration of a callback.
     #[track_caller]
     pub fn new(args: &[AfbDataV4], argc: u32, status: i32) -> Self {
-        AfbRqtData { count: argc, status, argsv4: args.to_owned() }
+        AfbRqtData { count: argc, status, argsv4: args.to_owned(), owned: false }
+    }
+
+    /// Take a reference on each data, they are released when the returned object is dropped
+    /// (or handed over to libafb when replied).
+    #[track_caller]
+    pub fn new_owned(args: &[AfbDataV4], argc: u32, status: i32) -> Self {
+        let data = AfbRqtData { count: argc, status, argsv4: args.to_owned(), owned: true };
+        data.addref();
+        data
+    }
+
+    pub fn is_owned(&self) -> bool {
+        self.owned
     }
 
     #[track_caller]
@@ -822,8 +868,15 @@ impl AfbRqtData {
 
 impl Clone for AfbRqtData {
     fn clone(&self) -> Self {
-        self.addref();
-        AfbRqtData { count: self.count, status: self.status, argsv4: self.argsv4.clone() }
+        AfbRqtData::new_owned(&self.argsv4, self.count, self.status)
+    }
+}
+
+impl Drop for AfbRqtData {
+    fn drop(&mut self) {
+        if self.owned {
+            self.unref();
+        }
     }
 }
 
@@ -963,7 +1016,9 @@ impl ConvertResponse<AfbParams> for AfbParams {
 // proxy converter when AfbRqtData is push reply
 impl ConvertResponse<AfbRqtData> for AfbParams {
     #[track_caller]
-    fn export(data: AfbRqtData) -> AfbExportResponse {
+    fn export(mut data: AfbRqtData) -> AfbExportResponse {
+        // owned references move to the response, borrowed ones are forwarded as before
+        data.owned = false;
         let mut param = AfbParams::new();
         for idx in 0..data.count {
             let datav4 = data.argsv4[idx as usize];
@@ -1250,6 +1250,250 @@ impl JsoncObj {
         Ok(())
     }
 
+    /// Check value against a JSON-Schema subset: type, enum, const, required, properties,
+    /// additionalProperties, items, minItems, maxItems, minLength, maxLength, minimum,
+    /// maximum, exclusiveMinimum, exclusiveMaximum, allOf, anyOf and oneOf.
+    /// On failure, error info starts with the JSON path of the invalid value ($.key[idx]).
+    #[track_caller]
+    pub fn validate(&self, jschema: &JsoncObj) -> Result<(), AfbError> {
+        self.schema_check("$", jschema)
+    }
+
+    // json-c does not convert int to double, schema bounds may use both
+    #[track_caller]
+    fn schema_number(jvalue: &JsoncObj) -> Result<f64, AfbError> {
+        match jvalue.get_type() {
+            Jtype::Int => Ok(jvalue.get_as::<i64>()? as f64),
+            _ => jvalue.get_as::<f64>(),
+        }
+    }
+
+    #[track_caller]
+    fn schema_check(&self, path: &str, jschema: &JsoncObj) -> Result<(), AfbError> {
+        if !jschema.is_type(Jtype::Object) {
+            return afb_error!("jsonc-schema", "{}: schema is not a json object", path);
+        }
+
+        if let Some(jtypes) = jschema.optional::<JsoncObj>("type")? {
+            let expected = if jtypes.is_type(Jtype::Array) {
+                let mut types = Vec::new();
+                for idx in 0..jtypes.count()? {
+                    types.push(jtypes.index::<String>(idx)?);
+                }
+                types
+            } else {
+                vec![jtypes.get_as::<String>()?]
+            };
+            let received = self.get_type();
+            let matching = expected.iter().any(|name| match name.as_str() {
+                "object" => received == Jtype::Object,
+                "array" => received == Jtype::Array,
+                "string" => received == Jtype::String,
+                "boolean" => received == Jtype::Bool,
+                "integer" => received == Jtype::Int,
+                "number" => received == Jtype::Int || received == Jtype::Float,
+                "null" => received == Jtype::Null,
+                _ => false,
+            });
+            if !matching {
+                return afb_error!(
+                    "jsonc-schema",
+                    "{}: invalid type received:{:?} expected:{}",
+                    path,
+                    received,
+                    jtypes
+                );
+            }
+        }
+
+        if let Some(jconst) = jschema.optional::<JsoncObj>("const")? {
+            if self.equal(path, jconst.clone(), Jequal::Full).is_err() {
+                return afb_error!("jsonc-schema", "{}: value:{} expected:{}", path, self, jconst);
+            }
+        }
+
+        if let Some(jenum) = jschema.optional::<JsoncObj>("enum")? {
+            let mut found = false;
+            for idx in 0..jenum.count()? {
+                if self.equal(path, jenum.index::<JsoncObj>(idx)?, Jequal::Full).is_ok() {
+                    found = true;
+                    break;
+                }
+            }
+            if !found {
+                return afb_error!(
+                    "jsonc-schema",
+                    "{}: value:{} not in enum:{}",
+                    path,
+                    self,
+                    jenum
+                );
+            }
+        }
+
+        match self.get_type() {
+            Jtype::Object => {
+                if let Some(jrequired) = jschema.optional::<JsoncObj>("required")? {
+                    for idx in 0..jrequired.count()? {
+                        let key = jrequired.index::<String>(idx)?;
+                        if self.optional::<JsoncObj>(&key)?.is_none() {
+                            return afb_error!(
+                                "jsonc-schema",
+                                "{}.{}: required key missing",
+                                path,
+                                key
+                            );
+                        }
+                    }
+                }
+                let jproperties = jschema.optional::<JsoncObj>("properties")?;
+                let jadditional = jschema.optional::<JsoncObj>("additionalProperties")?;
+                for entry in self.expand()? {
+                    let entry_path = format!("{}.{}", path, entry.key);
+                    let jproperty = match &jproperties {
+                        Some(jproperties) => jproperties.optional::<JsoncObj>(&entry.key)?,
+                        None => None,
+                    };
+                    match (jproperty, &jadditional) {
+                        (Some(jproperty), _) => entry.obj.schema_check(&entry_path, &jproperty)?,
+                        (None, Some(jadditional)) if jadditional.is_type(Jtype::Bool) => {
+                            if !jadditional.get_as::<bool>()? {
+                                return afb_error!(
+                                    "jsonc-schema",
+                                    "{}: additional property not allowed",
+                                    entry_path
+                                );
+                            }
+                        },
+                        (None, Some(jadditional)) => {
+                            entry.obj.schema_check(&entry_path, jadditional)?
+                        },
+                        (None, None) => {},
+                    }
+                }
+            },
+            Jtype::Array => {
+                let count = self.count()?;
+                if let Some(min) = jschema.optional::<u64>("minItems")? {
+                    if (count as u64) < min {
+                        return afb_error!(
+                            "jsonc-schema",
+                            "{}: minItems:{} count:{}",
+                            path,
+                            min,
+                            count
+                        );
+                    }
+                }
+                if let Some(max) = jschema.optional::<u64>("maxItems")? {
+                    if (count as u64) > max {
+                        return afb_error!(
+                            "jsonc-schema",
+                            "{}: maxItems:{} count:{}",
+                            path,
+                            max,
+                            count
+                        );
+                    }
+                }
+                if let Some(jitems) = jschema.optional::<JsoncObj>("items")? {
+                    for idx in 0..count {
+                        let slot_path = format!("{}[{}]", path, idx);
+                        self.index::<JsoncObj>(idx)?.schema_check(&slot_path, &jitems)?;
+                    }
+                }
+            },
+            Jtype::String => {
+                let length = self.get_as::<String>()?.chars().count() as u64;
+                if let Some(min) = jschema.optional::<u64>("minLength")? {
+                    if length < min {
+                        return afb_error!(
+                            "jsonc-schema",
+                            "{}: minLength:{} length:{}",
+                            path,
+                            min,
+                            length
+                        );
+                    }
+                }
+                if let Some(max) = jschema.optional::<u64>("maxLength")? {
+                    if length > max {
+                        return afb_error!(
+                            "jsonc-schema",
+                            "{}: maxLength:{} length:{}",
+                            path,
+                            max,
+                            length
+                        );
+                    }
+                }
+            },
+            Jtype::Int | Jtype::Float => {
+                let value = Self::schema_number(self)?;
+                for keyword in ["minimum", "maximum", "exclusiveMinimum", "exclusiveMaximum"] {
+                    if let Some(jlimit) = jschema.optional::<JsoncObj>(keyword)? {
+                        let limit = Self::schema_number(&jlimit)?;
+                        let valid = match keyword {
+                            "minimum" => value >= limit,
+                            "maximum" => value <= limit,
+                            "exclusiveMinimum" => value > limit,
+                            _ => value < limit,
+                        };
+                        if !valid {
+                            return afb_error!(
+                                "jsonc-schema",
+                                "{}: {}:{} value:{}",
+                                path,
+                                keyword,
+                                limit,
+                                value
+                            );
+                        }
+                    }
+                }
+            },
+            _ => {},
+        }
+
+        if let Some(jall) = jschema.optional::<JsoncObj>("allOf")? {
+            for idx in 0..jall.count()? {
+                self.schema_check(path, &jall.index::<JsoncObj>(idx)?)?;
+            }
+        }
+
+        if let Some(jany) = jschema.optional::<JsoncObj>("anyOf")? {
+            let mut matching = false;
+            for idx in 0..jany.count()? {
+                if self.schema_check(path, &jany.index::<JsoncObj>(idx)?).is_ok() {
+                    matching = true;
+                    break;
+                }
+            }
+            if !matching {
+                return afb_error!("jsonc-schema", "{}: value:{} does not match anyOf", path, self);
+            }
+        }
+
+        if let Some(jone) = jschema.optional::<JsoncObj>("oneOf")? {
+            let mut matching = 0;
+            for idx in 0..jone.count()? {
+                if self.schema_check(path, &jone.index::<JsoncObj>(idx)?).is_ok() {
+                    matching += 1;
+                }
+            }
+            if matching != 1 {
+                return afb_error!(
+                    "jsonc-schema",
+                    "{}: value:{} matches {} oneOf schemas",
+                    path,
+                    self,
+                    matching
+                );
+            }
+        }
+        Ok(())
+    }
+
     #[track_caller]
     pub fn parse(json_str: &str) -> Result<JsoncObj, AfbError> {
         unsafe {
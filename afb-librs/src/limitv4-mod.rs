@@ -0,0 +1,198 @@
+/*
+ * Copyright (C) 2015-2023 IoT.bzh Company
+ * Author: Fulup Ar Foll <fulup@iot.bzh>
+ *
+ * $RP_BEGIN_LICENSE$
+ * Commercial License Usage
+ *  Licensees holding valid commercial IoT.bzh licenses may use this file in
+ *  accordance with the commercial license agreement provided with the
+ *  Software or, alternatively, in accordance with the terms contained in
+ *  a written agreement between you and The IoT.bzh Company. For licensing terms
+ *  and conditions see https://www.iot.bzh/terms-conditions. For further
+ *  information use the contact form at https://www.iot.bzh/contact.
+ *
+ * GNU General Public License Usage
+ *  Alternatively, this file may be used under the terms of the GNU General
+ *  Public license version 3. This license is as published by the Free Software
+ *  Foundation and appearing in the file LICENSE.GPLv3 included in the packaging
+ *  of this file. Please review the following information to ensure the GNU
+ *  General Public License requirements will be met
+ *  https://www.gnu.org/licenses/gpl-3.0.html.
+ * $RP_END_LICENSE$
+ */
+
+// Verb/group admission policies checked by api_verbs_cb before user callback: max in-flight
+// requests, token bucket rate and per session in-flight quota. Acquired slots are released
+// when the request is replied or when its last handle is dropped.
+
+use crate::prelude::*;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Reply status of requests rejected by a limit (-EBUSY).
+pub const AFB_LIMIT_STATUS: i32 = -16;
+
+struct AfbTokenBucket {
+    tokens: f64,
+    last: Instant,
+}
+
+pub struct AfbLimit {
+    _uid: &'static str,
+    max_inflight: usize,
+    rate: u32,
+    burst: u32,
+    session_quota: usize,
+    inflight: AtomicUsize,
+    bucket: Mutex<AfbTokenBucket>,
+    sessions: Mutex<HashMap<String, usize>>,
+}
+
+impl AfbLimit {
+    pub fn new(uid: &'static str) -> &'static mut Self {
+        Box::leak(Box::new(AfbLimit {
+            _uid: uid,
+            max_inflight: 0,
+            rate: 0,
+            burst: 0,
+            session_quota: 0,
+            inflight: AtomicUsize::new(0),
+            bucket: Mutex::new(AfbTokenBucket { tokens: 0.0, last: Instant::now() }),
+            sessions: Mutex::new(HashMap::new()),
+        }))
+    }
+
+    /// Build a limit from `{"max_inflight":8, "rate":100, "burst":20, "session_quota":2}`,
+    /// every key is optional and 0 means unlimited.
+    #[track_caller]
+    pub fn from_jsonc(uid: &'static str, jlimit: &JsoncObj) -> Result<&'static mut Self, AfbError> {
+        if !jlimit.is_type(Jtype::Object) {
+            return afb_error!("limit-config", "limit:{} expect a json object", uid);
+        }
+        let limit = AfbLimit::new(uid);
+        limit.set_max_inflight(jlimit.default::<u32>("max_inflight", 0)? as usize);
+        let rate = jlimit.default::<u32>("rate", 0)?;
+        limit.set_rate(rate, jlimit.default::<u32>("burst", rate)?);
+        limit.set_session_quota(jlimit.default::<u32>("session_quota", 0)? as usize);
+        Ok(limit)
+    }
+
+    pub fn set_max_inflight(&mut self, value: usize) -> &mut Self {
+        self.max_inflight = value;
+        self
+    }
+
+    /// Token bucket refilled with `rate` tokens per second up to `burst`.
+    pub fn set_rate(&mut self, rate: u32, burst: u32) -> &mut Self {
+        self.rate = rate;
+        self.burst = burst.max(1);
+        self.bucket.get_mut().unwrap().tokens = self.burst as f64;
+        self
+    }
+
+    /// Max in-flight requests per client session.
+    pub fn set_session_quota(&mut self, value: usize) -> &mut Self {
+        self.session_quota = value;
+        self
+    }
+
+    pub fn get_uid(&self) -> &'static str {
+        self._uid
+    }
+
+    pub fn get_inflight(&self) -> usize {
+        self.inflight.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn need_session(&self) -> bool {
+        self.session_quota > 0
+    }
+
+    #[track_caller]
+    pub(crate) fn acquire(&'static self, session: Option<&str>) -> Result<AfbLimitGuard, AfbError> {
+        if self.max_inflight > 0 {
+            let accepted =
+                self.inflight.fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| {
+                    (count < self.max_inflight).then_some(count + 1)
+                });
+            if accepted.is_err() {
+                return afb_error!(
+                    "limit-inflight",
+                    "limit:{} max in-flight requests:{} reached",
+                    self._uid,
+                    self.max_inflight
+                );
+            }
+        } else {
+            self.inflight.fetch_add(1, Ordering::AcqRel);
+        }
+        // from now on dropping the guard releases what was acquired
+        let mut guard = AfbLimitGuard { limit: self, session: None };
+
+        if self.rate > 0 {
+            let mut bucket = self.bucket.lock().unwrap();
+            let now = Instant::now();
+            let refill = now.duration_since(bucket.last).as_secs_f64() * self.rate as f64;
+            bucket.tokens = (bucket.tokens + refill).min(self.burst as f64);
+            bucket.last = now;
+            if bucket.tokens < 1.0 {
+                return afb_error!(
+                    "limit-rate",
+                    "limit:{} rate:{}/s burst:{} exceeded",
+                    self._uid,
+                    self.rate,
+                    self.burst
+                );
+            }
+            bucket.tokens -= 1.0;
+        }
+
+        if self.session_quota > 0 {
+            let uuid = match session {
+                Some(uuid) => uuid.to_string(),
+                None => {
+                    return afb_error!(
+                        "limit-session",
+                        "limit:{} request has no session",
+                        self._uid
+                    )
+                },
+            };
+            let mut sessions = self.sessions.lock().unwrap();
+            let count = sessions.entry(uuid.clone()).or_insert(0);
+            if *count >= self.session_quota {
+                return afb_error!(
+                    "limit-session",
+                    "limit:{} session quota:{} reached",
+                    self._uid,
+                    self.session_quota
+                );
+            }
+            *count += 1;
+            guard.session = Some(uuid);
+        }
+        Ok(guard)
+    }
+}
+
+pub(crate) struct AfbLimitGuard {
+    limit: &'static AfbLimit,
+    session: Option<String>,
+}
+
+impl Drop for AfbLimitGuard {
+    fn drop(&mut self) {
+        self.limit.inflight.fetch_sub(1, Ordering::AcqRel);
+        if let Some(uuid) = self.session.take() {
+            let mut sessions = self.limit.sessions.lock().unwrap();
+            if let Some(count) = sessions.get_mut(&uuid) {
+                *count -= 1;
+                if *count == 0 {
+                    sessions.remove(&uuid);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,181 @@
+/*
+ * Copyright (C) 2015-2023 IoT.bzh Company
+ * Author: Fulup Ar Foll <fulup@iot.bzh>
+ *
+ * $RP_BEGIN_LICENSE$
+ * Commercial License Usage
+ *  Licensees holding valid commercial IoT.bzh licenses may use this file in
+ *  accordance with the commercial license agreement provided with the
+ *  Software or, alternatively, in accordance with the terms contained in
+ *  a written agreement between you and The IoT.bzh Company. For licensing terms
+ *  and conditions see https://www.iot.bzh/terms-conditions. For further
+ *  information use the contact form at https://www.iot.bzh/contact.
+ *
+ * GNU General Public License Usage
+ *  Alternatively, this file may be used under the terms of the GNU General
+ *  Public license version 3. This license is as published by the Free Software
+ *  Foundation and appearing in the file LICENSE.GPLv3 included in the packaging
+ *  of this file. Please review the following information to ensure the GNU
+ *  General Public License requirements will be met
+ *  https://www.gnu.org/licenses/gpl-3.0.html.
+ * $RP_END_LICENSE$
+ */
+
+// Per verb request metrics: counters are updated from api_verbs_cb (call) and from
+// AfbRequest::reply (status + latency measured from callback entry).
+
+use crate::prelude::*;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+/// Latency histogram upper bounds in micro-seconds, last bucket counts everything above.
+pub const AFB_LATENCY_BUCKETS_US: [u64; 10] =
+    [100, 500, 1_000, 5_000, 10_000, 50_000, 100_000, 500_000, 1_000_000, 5_000_000];
+
+/// Verb metrics snapshot returned by `AfbVerbMetrics::get_stats`.
+#[derive(Clone, Debug, Default)]
+pub struct AfbVerbStats {
+    pub calls: u64,
+    pub success: u64,
+    pub errors: Vec<(i32, u64)>,
+    pub inflight: i64,
+    pub unreplied: u64,
+    /// one count per `AFB_LATENCY_BUCKETS_US` slot plus overflow
+    pub latency_buckets: Vec<u64>,
+    pub latency_sum_us: u64,
+    pub latency_max_us: u64,
+}
+
+pub struct AfbVerbMetrics {
+    calls: AtomicU64,
+    success: AtomicU64,
+    unreplied: AtomicU64,
+    inflight: AtomicI64,
+    errors: Mutex<BTreeMap<i32, u64>>,
+    buckets: [AtomicU64; AFB_LATENCY_BUCKETS_US.len() + 1],
+    latency_sum_us: AtomicU64,
+    latency_max_us: AtomicU64,
+}
+
+impl Default for AfbVerbMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AfbVerbMetrics {
+    pub fn new() -> Self {
+        AfbVerbMetrics {
+            calls: AtomicU64::new(0),
+            success: AtomicU64::new(0),
+            unreplied: AtomicU64::new(0),
+            inflight: AtomicI64::new(0),
+            errors: Mutex::new(BTreeMap::new()),
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+            latency_sum_us: AtomicU64::new(0),
+            latency_max_us: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn started(&self) {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        self.inflight.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn replied(&self, status: i32, elapsed: Duration) {
+        self.inflight.fetch_sub(1, Ordering::Relaxed);
+        if status >= 0 {
+            self.success.fetch_add(1, Ordering::Relaxed);
+        } else {
+            *self
+                .errors
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry(status)
+                .or_insert(0) += 1;
+        }
+
+        let elapsed_us = elapsed.as_micros() as u64;
+        let slot = AFB_LATENCY_BUCKETS_US
+            .iter()
+            .position(|bound| elapsed_us <= *bound)
+            .unwrap_or(AFB_LATENCY_BUCKETS_US.len());
+        self.buckets[slot].fetch_add(1, Ordering::Relaxed);
+        self.latency_sum_us.fetch_add(elapsed_us, Ordering::Relaxed);
+        self.latency_max_us.fetch_max(elapsed_us, Ordering::Relaxed);
+    }
+
+    // every request handle was dropped without any reply
+    pub(crate) fn dropped(&self) {
+        self.inflight.fetch_sub(1, Ordering::Relaxed);
+        self.unreplied.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn get_stats(&self) -> AfbVerbStats {
+        AfbVerbStats {
+            calls: self.calls.load(Ordering::Relaxed),
+            success: self.success.load(Ordering::Relaxed),
+            errors: self
+                .errors
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+                .map(|(k, v)| (*k, *v))
+                .collect(),
+            inflight: self.inflight.load(Ordering::Relaxed),
+            unreplied: self.unreplied.load(Ordering::Relaxed),
+            latency_buckets: self.buckets.iter().map(|b| b.load(Ordering::Relaxed)).collect(),
+            latency_sum_us: self.latency_sum_us.load(Ordering::Relaxed),
+            latency_max_us: self.latency_max_us.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn reset(&self) {
+        self.calls.store(0, Ordering::Relaxed);
+        self.success.store(0, Ordering::Relaxed);
+        self.unreplied.store(0, Ordering::Relaxed);
+        self.errors.lock().unwrap_or_else(PoisonError::into_inner).clear();
+        for bucket in &self.buckets {
+            bucket.store(0, Ordering::Relaxed);
+        }
+        self.latency_sum_us.store(0, Ordering::Relaxed);
+        self.latency_max_us.store(0, Ordering::Relaxed);
+    }
+
+    #[track_caller]
+    pub fn to_jsonc(&self) -> Result<JsoncObj, AfbError> {
+        let stats = self.get_stats();
+        let jstats = JsoncObj::new();
+        jstats.add("calls", stats.calls)?;
+        jstats.add("success", stats.success)?;
+        jstats.add("inflight", stats.inflight)?;
+        jstats.add("unreplied", stats.unreplied)?;
+
+        let jerrors = JsoncObj::new();
+        for (status, count) in &stats.errors {
+            jerrors.add(&status.to_string(), *count)?;
+        }
+        jstats.add("errors", jerrors)?;
+
+        let jbuckets = JsoncObj::new();
+        for (idx, count) in stats.latency_buckets.iter().enumerate() {
+            let label = match AFB_LATENCY_BUCKETS_US.get(idx) {
+                Some(bound) => format!("le_{}us", bound),
+                None => "inf".to_string(),
+            };
+            jbuckets.add(&label, *count)?;
+        }
+        let jlatency = JsoncObj::new();
+        jlatency.add("sum_us", stats.latency_sum_us)?;
+        jlatency.add("max_us", stats.latency_max_us)?;
+        let replied = stats.success + stats.errors.iter().map(|(_, count)| count).sum::<u64>();
+        if let Some(average) = stats.latency_sum_us.checked_div(replied) {
+            jlatency.add("avg_us", average)?;
+        }
+        jlatency.add("buckets", jbuckets)?;
+        jstats.add("latency", jlatency)?;
+        Ok(jstats)
+    }
+}
@@ -0,0 +1,342 @@
+/*
+ * Copyright (C) 2015-2023 IoT.bzh Company
+ * Author: Fulup Ar Foll <fulup@iot.bzh>
+ *
+ * $RP_BEGIN_LICENSE$
+ * Commercial License Usage
+ *  Licensees holding valid commercial IoT.bzh licenses may use this file in
+ *  accordance with the commercial license agreement provided with the
+ *  Software or, alternatively, in accordance with the terms contained in
+ *  a written agreement between you and The IoT.bzh Company. For licensing terms
+ *  and conditions see https://www.iot.bzh/terms-conditions. For further
+ *  information use the contact form at https://www.iot.bzh/contact.
+ *
+ * GNU General Public License Usage
+ *  Alternatively, this file may be used under the terms of the GNU General
+ *  Public license version 3. This license is as published by the Free Software
+ *  Foundation and appearing in the file LICENSE.GPLv3 included in the packaging
+ *  of this file. Please review the following information to ensure the GNU
+ *  General Public License requirements will be met
+ *  https://www.gnu.org/licenses/gpl-3.0.html.
+ * $RP_END_LICENSE$
+ */
+
+// OpenMetrics text exposition (cargo feature "openmetrics"). Builtin verb metrics are
+// rendered from AfbVerbMetrics, bindings add their own counters/gauges to a registry
+// attached to their api with `AfbApi::set_metrics_registry`.
+
+use crate::prelude::*;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AfbMetricKind {
+    Counter,
+    Gauge,
+}
+
+impl AfbMetricKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            AfbMetricKind::Counter => "counter",
+            AfbMetricKind::Gauge => "gauge",
+        }
+    }
+}
+
+/// Binding defined metric, value is an f64 stored as atomic bits.
+pub struct AfbMetric {
+    name: &'static str,
+    help: &'static str,
+    kind: AfbMetricKind,
+    value: AtomicU64,
+}
+
+impl AfbMetric {
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn get_kind(&self) -> AfbMetricKind {
+        self.kind
+    }
+
+    pub fn get(&self) -> f64 {
+        f64::from_bits(self.value.load(Ordering::Relaxed))
+    }
+
+    pub fn inc(&self) {
+        self.add(1.0)
+    }
+
+    /// Counters only move forward, negative values are ignored for them.
+    pub fn add(&self, value: f64) {
+        if self.kind == AfbMetricKind::Counter && value < 0.0 {
+            return;
+        }
+        let _ = self.value.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+            Some((f64::from_bits(bits) + value).to_bits())
+        });
+    }
+
+    pub fn sub(&self, value: f64) {
+        self.add(-value)
+    }
+
+    #[track_caller]
+    pub fn set(&self, value: f64) -> Result<(), AfbError> {
+        if self.kind == AfbMetricKind::Counter {
+            return afb_error!("metric-set", "metric:{} is a counter, use add/inc", self.name);
+        }
+        self.value.store(value.to_bits(), Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+pub struct AfbMetricsRegistry {
+    _uid: &'static str,
+    metrics: Mutex<Vec<&'static AfbMetric>>,
+}
+
+impl AfbMetricsRegistry {
+    pub fn new(uid: &'static str) -> &'static Self {
+        Box::leak(Box::new(AfbMetricsRegistry { _uid: uid, metrics: Mutex::new(Vec::new()) }))
+    }
+
+    pub fn get_uid(&self) -> &'static str {
+        self._uid
+    }
+
+    #[track_caller]
+    fn register(
+        &self,
+        name: &'static str,
+        help: &'static str,
+        kind: AfbMetricKind,
+    ) -> Result<&'static AfbMetric, AfbError> {
+        let mut chars = name.chars();
+        let valid = match chars.next() {
+            Some(first) => {
+                (first.is_ascii_alphabetic() || first == '_' || first == ':')
+                    && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+            },
+            None => false,
+        };
+        if !valid || name.starts_with("afb_") {
+            return afb_error!(
+                "metric-register",
+                "registry:{} invalid metric name:{} (expect [a-zA-Z_:][a-zA-Z0-9_:]* without afb_ prefix)",
+                self._uid,
+                name
+            );
+        }
+
+        let mut metrics = self.metrics.lock().unwrap_or_else(PoisonError::into_inner);
+        if metrics.iter().any(|metric| metric.name == name) {
+            return afb_error!(
+                "metric-register",
+                "registry:{} metric:{} already registered",
+                self._uid,
+                name
+            );
+        }
+        let metric = Box::leak(Box::new(AfbMetric {
+            name,
+            help,
+            kind,
+            value: AtomicU64::new(0f64.to_bits()),
+        }));
+        metrics.push(metric);
+        Ok(metric)
+    }
+
+    /// Monotonic counter exported as `<name>_total`.
+    #[track_caller]
+    pub fn counter(
+        &self,
+        name: &'static str,
+        help: &'static str,
+    ) -> Result<&'static AfbMetric, AfbError> {
+        self.register(name, help, AfbMetricKind::Counter)
+    }
+
+    #[track_caller]
+    pub fn gauge(
+        &self,
+        name: &'static str,
+        help: &'static str,
+    ) -> Result<&'static AfbMetric, AfbError> {
+        self.register(name, help, AfbMetricKind::Gauge)
+    }
+
+    pub fn get_metrics(&self) -> Vec<&'static AfbMetric> {
+        self.metrics.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+}
+
+fn openmetrics_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+fn openmetrics_family(
+    text: &mut String,
+    name: &str,
+    kind: &str,
+    help: &str,
+) -> Result<(), std::fmt::Error> {
+    writeln!(text, "# TYPE {} {}", name, kind)?;
+    writeln!(text, "# HELP {} {}", name, openmetrics_label(help))
+}
+
+fn openmetrics_verbs(text: &mut String, api: &AfbApi) -> Result<(), std::fmt::Error> {
+    let apiname = openmetrics_label(api.get_name());
+    let verbs: Vec<(String, AfbVerbStats)> = api
+        .get_all_verbs()
+        .iter()
+        .map(|verb| (openmetrics_label(verb.get_name()), verb.get_metrics().get_stats()))
+        .collect();
+
+    openmetrics_family(text, "afb_verb_requests", "counter", "verb requests received")?;
+    for (verb, stats) in &verbs {
+        writeln!(
+            text,
+            "afb_verb_requests_total{{api=\"{}\",verb=\"{}\"}} {}",
+            apiname, verb, stats.calls
+        )?;
+    }
+
+    openmetrics_family(text, "afb_verb_replies", "counter", "verb replies by status")?;
+    for (verb, stats) in &verbs {
+        writeln!(
+            text,
+            "afb_verb_replies_total{{api=\"{}\",verb=\"{}\",status=\"success\"}} {}",
+            apiname, verb, stats.success
+        )?;
+        for (status, count) in &stats.errors {
+            writeln!(
+                text,
+                "afb_verb_replies_total{{api=\"{}\",verb=\"{}\",status=\"{}\"}} {}",
+                apiname, verb, status, count
+            )?;
+        }
+    }
+
+    openmetrics_family(
+        text,
+        "afb_verb_unreplied",
+        "counter",
+        "verb requests released without reply",
+    )?;
+    for (verb, stats) in &verbs {
+        writeln!(
+            text,
+            "afb_verb_unreplied_total{{api=\"{}\",verb=\"{}\"}} {}",
+            apiname, verb, stats.unreplied
+        )?;
+    }
+
+    openmetrics_family(text, "afb_verb_inflight", "gauge", "verb requests waiting for reply")?;
+    for (verb, stats) in &verbs {
+        writeln!(
+            text,
+            "afb_verb_inflight{{api=\"{}\",verb=\"{}\"}} {}",
+            apiname, verb, stats.inflight
+        )?;
+    }
+
+    openmetrics_family(
+        text,
+        "afb_verb_latency_seconds",
+        "histogram",
+        "verb callback entry to reply latency",
+    )?;
+    for (verb, stats) in &verbs {
+        let mut cumulative = 0;
+        for (idx, count) in stats.latency_buckets.iter().enumerate() {
+            cumulative += count;
+            let bound = match AFB_LATENCY_BUCKETS_US.get(idx) {
+                Some(bound_us) => format!("{}", *bound_us as f64 / 1_000_000.0),
+                None => "+Inf".to_string(),
+            };
+            writeln!(
+                text,
+                "afb_verb_latency_seconds_bucket{{api=\"{}\",verb=\"{}\",le=\"{}\"}} {}",
+                apiname, verb, bound, cumulative
+            )?;
+        }
+        writeln!(
+            text,
+            "afb_verb_latency_seconds_count{{api=\"{}\",verb=\"{}\"}} {}",
+            apiname, verb, cumulative
+        )?;
+        writeln!(
+            text,
+            "afb_verb_latency_seconds_sum{{api=\"{}\",verb=\"{}\"}} {}",
+            apiname,
+            verb,
+            stats.latency_sum_us as f64 / 1_000_000.0
+        )?;
+    }
+    Ok(())
+}
+
+fn openmetrics_registry(
+    text: &mut String,
+    api: &AfbApi,
+    registry: &AfbMetricsRegistry,
+) -> Result<(), std::fmt::Error> {
+    let apiname = openmetrics_label(api.get_name());
+    for metric in registry.get_metrics() {
+        openmetrics_family(text, metric.name, metric.kind.as_str(), metric.help)?;
+        let suffix = match metric.kind {
+            AfbMetricKind::Counter => "_total",
+            AfbMetricKind::Gauge => "",
+        };
+        writeln!(text, "{}{}{{api=\"{}\"}} {}", metric.name, suffix, apiname, metric.get())?;
+    }
+    Ok(())
+}
+
+/// OpenMetrics text exposition of api verbs metrics and of its registry when any.
+#[track_caller]
+pub fn afb_openmetrics(api: &AfbApi) -> Result<String, AfbError> {
+    let mut text = String::new();
+    let mut render = || -> Result<(), std::fmt::Error> {
+        openmetrics_verbs(&mut text, api)?;
+        if let Some(registry) = api.get_metrics_registry() {
+            openmetrics_registry(&mut text, api, registry)?;
+        }
+        writeln!(text, "# EOF")
+    };
+    if render().is_err() {
+        return afb_error!("openmetrics-render", "api:{} fail to format metrics", api.get_uid());
+    }
+    Ok(text)
+}
+
+/// # Safety
+/// `rqtv4` is provided by libafb; `_args` must point to an array of `argc` items.
+#[no_mangle]
+pub unsafe extern "C" fn api_metrics_cb(
+    rqtv4: cglue::afb_req_t,
+    argc: u32,
+    args: *const cglue::afb_data_t,
+) {
+    crate::apiv4::afb_builtin_catch_panic("builtin-metrics", rqtv4, || {
+        api_metrics(rqtv4, argc, args)
+    });
+}
+
+unsafe fn api_metrics(rqtv4: cglue::afb_req_t, _argc: u32, _args: *const cglue::afb_data_t) {
+    let api_ref = unsafe {
+        let vcbdata = cglue::afb_req_get_vcbdata(rqtv4);
+        &*(vcbdata as *const AfbApi)
+    };
+
+    let request = AfbRequest::from_raw(rqtv4);
+    match afb_openmetrics(api_ref) {
+        Ok(text) => request.reply(text, 0),
+        Err(error) => request.reply(error, -100),
+    }
+}
@@ -0,0 +1,502 @@
+/*
+ * Copyright (C) 2015-2023 IoT.bzh Company
+ * Author: Fulup Ar Foll <fulup@iot.bzh>
+ *
+ * $RP_BEGIN_LICENSE$
+ * Commercial License Usage
+ *  Licensees holding valid commercial IoT.bzh licenses may use this file in
+ *  accordance with the commercial license agreement provided with the
+ *  Software or, alternatively, in accordance with the terms contained in
+ *  a written agreement between you and The IoT.bzh Company. For licensing terms
+ *  and conditions see https://www.iot.bzh/terms-conditions. For further
+ *  information use the contact form at https://www.iot.bzh/contact.
+ *
+ * GNU General Public License Usage
+ *  Alternatively, this file may be used under the terms of the GNU General
+ *  Public license version 3. This license is as published by the Free Software
+ *  Foundation and appearing in the file LICENSE.GPLv3 included in the packaging
+ *  of this file. Please review the following information to ensure the GNU
+ *  General Public License requirements will be met
+ *  https://www.gnu.org/licenses/gpl-3.0.html.
+ * $RP_END_LICENSE$
+ */
+
+// Session persistence (cargo feature "session-persist"). Registered AfbSessionStore value types
+// and legacy api session objects (set_session) are serialized with serde into a local JSON or
+// CBOR file when they changed (checked on each session sweep tick) and at binding exit. At next
+// start shared values are restored under the same session uuid, ready for the client to
+// reappear, until they expire like any other session. Legacy objects wait for the first session
+// access of their client on their api.
+
+use crate::prelude::*;
+use crate::sessionv4::{
+    api_session_restore, api_sessions_visit, mark_dirty, start_sweep, store_restore, store_slots,
+    AfbSessionSlot, AFB_SESSION_DIRTY,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::any::TypeId;
+use std::collections::BTreeMap;
+use std::os::unix::fs::OpenOptionsExt;
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AfbSessionFormat {
+    Json,
+    Cbor,
+}
+
+// session uuid -> type key -> serialized value
+type AfbSessionSnapshot = BTreeMap<String, BTreeMap<String, serde_json::Value>>;
+
+// shared session uuid -> creation time (unix seconds) and type key -> serialized value
+type AfbSharedSnapshot = BTreeMap<String, (u64, BTreeMap<String, serde_json::Value>)>;
+
+// shared sessions, then api uid -> legacy session objects
+type AfbPersistSnapshot = (AfbSharedSnapshot, BTreeMap<String, AfbSessionSnapshot>);
+
+fn unix_now() -> Duration {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default()
+}
+
+struct AfbPersistType {
+    key: &'static str,
+    type_id: TypeId,
+    save: fn(&Arc<dyn AfbSessionSlot>) -> Result<serde_json::Value, AfbError>,
+    restore: fn(serde_json::Value) -> Result<Arc<dyn AfbSessionSlot>, AfbError>,
+}
+
+#[track_caller]
+fn save_slot<T>(slot: &Arc<dyn AfbSessionSlot>) -> Result<serde_json::Value, AfbError>
+where
+    T: AfbSessionData + Serialize,
+{
+    let value = match slot.clone().into_any().downcast::<Mutex<T>>() {
+        Ok(value) => value,
+        Err(_) => {
+            return afb_error!(
+                "session-any-cast",
+                "fail to restore <{}>",
+                std::any::type_name::<T>()
+            )
+        },
+    };
+    let value = value.lock().unwrap_or_else(PoisonError::into_inner);
+    match serde_json::to_value(&*value) {
+        Ok(jvalue) => Ok(jvalue),
+        Err(error) => afb_error!(
+            "session-persist",
+            "fail to serialize <{}> {}",
+            std::any::type_name::<T>(),
+            error
+        ),
+    }
+}
+
+#[track_caller]
+fn restore_slot<T>(jvalue: serde_json::Value) -> Result<Arc<dyn AfbSessionSlot>, AfbError>
+where
+    T: AfbSessionData + DeserializeOwned,
+{
+    match serde_json::from_value::<T>(jvalue) {
+        Ok(value) => Ok(Arc::new(Mutex::new(value))),
+        Err(error) => afb_error!(
+            "session-persist",
+            "fail to restore <{}> {}",
+            std::any::type_name::<T>(),
+            error
+        ),
+    }
+}
+
+struct AfbPersistApiType {
+    key: &'static str,
+    save: fn(&mut dyn AfbRqtSession) -> Option<Result<serde_json::Value, AfbError>>,
+    restore: fn(serde_json::Value) -> Result<Box<dyn AfbRqtSession + Send>, AfbError>,
+}
+
+// None when legacy object is not a `T`
+#[track_caller]
+fn save_api_session<T>(inner: &mut dyn AfbRqtSession) -> Option<Result<serde_json::Value, AfbError>>
+where
+    T: AfbRqtSession + Serialize + 'static,
+{
+    let value = inner.as_any().downcast_mut::<T>()?;
+    Some(match serde_json::to_value(&*value) {
+        Ok(jvalue) => Ok(jvalue),
+        Err(error) => afb_error!(
+            "session-persist",
+            "fail to serialize <{}> {}",
+            std::any::type_name::<T>(),
+            error
+        ),
+    })
+}
+
+#[track_caller]
+fn restore_api_session<T>(
+    jvalue: serde_json::Value,
+) -> Result<Box<dyn AfbRqtSession + Send>, AfbError>
+where
+    T: AfbRqtSession + Send + DeserializeOwned + 'static,
+{
+    match serde_json::from_value::<T>(jvalue) {
+        Ok(value) => Ok(Box::new(value)),
+        Err(error) => afb_error!(
+            "session-persist",
+            "fail to restore <{}> {}",
+            std::any::type_name::<T>(),
+            error
+        ),
+    }
+}
+
+static AFB_SESSION_PERSIST: AtomicPtr<AfbSessionPersist> = AtomicPtr::new(std::ptr::null_mut());
+
+/// Persistence backend of binding-wide shared sessions and legacy api sessions, one per binding.
+pub struct AfbSessionPersist {
+    path: String,
+    format: AfbSessionFormat,
+    types: Vec<AfbPersistType>,
+    api_types: Vec<AfbPersistApiType>,
+    // legacy objects last written, kept for those in use by a request at next save
+    saved_apis: Mutex<BTreeMap<String, AfbSessionSnapshot>>,
+}
+
+impl AfbSessionPersist {
+    pub fn new(path: &str) -> &'static mut Self {
+        let persist_box = Box::new(AfbSessionPersist {
+            path: path.to_string(),
+            format: AfbSessionFormat::Json,
+            types: Vec::new(),
+            api_types: Vec::new(),
+            saved_apis: Mutex::new(BTreeMap::new()),
+        });
+        Box::leak(persist_box)
+    }
+
+    pub fn set_format(&mut self, format: AfbSessionFormat) -> &mut Self {
+        self.format = format;
+        self
+    }
+
+    /// Persist shared values of type `T` under `key`, key should stay stable across binding
+    /// updates. Types not added here are not persisted.
+    pub fn add_type<T>(&mut self, key: &'static str) -> &mut Self
+    where
+        T: AfbSessionData + Serialize + DeserializeOwned,
+    {
+        self.types.push(AfbPersistType {
+            key,
+            type_id: TypeId::of::<T>(),
+            save: save_slot::<T>,
+            restore: restore_slot::<T>,
+        });
+        self
+    }
+
+    /// Persist legacy api session objects (`set_session`, `AfbSessionRegister!`) of type `T`
+    /// under `key`. Restored objects wait for their client in a binding-wide table, hence `Send`.
+    /// They attach to their api context on first session access.
+    pub fn add_api_session<T>(&mut self, key: &'static str) -> &mut Self
+    where
+        T: AfbRqtSession + Send + Serialize + DeserializeOwned + 'static,
+    {
+        self.api_types.push(AfbPersistApiType {
+            key,
+            save: save_api_session::<T>,
+            restore: restore_api_session::<T>,
+        });
+        self
+    }
+
+    /// Restore previous snapshot then save store on change and at binding exit.
+    #[track_caller]
+    pub fn finalize(&mut self) -> Result<&Self, AfbError> {
+        if !AFB_SESSION_PERSIST.load(Ordering::Acquire).is_null() {
+            return afb_error!("session-persist", "session persistence already defined");
+        }
+        let count = self.restore()?;
+        afb_log_msg!(
+            Notice,
+            None,
+            "session-persist restored {} session(s) from {}",
+            count,
+            self.path
+        );
+
+        AFB_SESSION_PERSIST.store(self, Ordering::Release);
+        AFB_SESSION_DIRTY.store(false, Ordering::Release);
+        start_sweep();
+        afb_on_exit("session-persist", afb_session_flush);
+        Ok(self)
+    }
+
+    pub fn get_path(&self) -> &str {
+        &self.path
+    }
+
+    /// Write every session holding persisted types: owner only temporary file, synced to disk
+    /// then renamed over previous snapshot. A legacy object in use by a request keeps its
+    /// previous content and is written again on next save.
+    #[track_caller]
+    pub fn save(&self) -> Result<(), AfbError> {
+        let now = unix_now();
+        let mut shared = AfbSharedSnapshot::new();
+        for (uuid, age, values) in store_slots() {
+            let mut jvalues = BTreeMap::new();
+            for (type_id, slot) in values {
+                if let Some(ptype) = self.types.iter().find(|ptype| ptype.type_id == type_id) {
+                    jvalues.insert(ptype.key.to_string(), (ptype.save)(&slot)?);
+                }
+            }
+            if !jvalues.is_empty() {
+                shared.insert(uuid, (now.saturating_sub(age).as_secs(), jvalues));
+            }
+        }
+
+        let mut apis = BTreeMap::<String, AfbSessionSnapshot>::new();
+        let mut status = Ok(());
+        let mut borrowed = false;
+        let mut saved_apis = self.saved_apis.lock().unwrap_or_else(PoisonError::into_inner);
+        api_sessions_visit(|api, uuid, inner| {
+            let inner = match inner {
+                Some(inner) => inner,
+                None => {
+                    borrowed = true;
+                    if let Some(jvalues) = saved_apis.get(api).and_then(|saved| saved.get(uuid)) {
+                        apis.entry(api.to_string())
+                            .or_default()
+                            .insert(uuid.to_string(), jvalues.clone());
+                    }
+                    return;
+                },
+            };
+            for ptype in &self.api_types {
+                match (ptype.save)(inner) {
+                    Some(Ok(jvalue)) => {
+                        let jvalues = BTreeMap::from([(ptype.key.to_string(), jvalue)]);
+                        apis.entry(api.to_string()).or_default().insert(uuid.to_string(), jvalues);
+                        break;
+                    },
+                    Some(Err(error)) => {
+                        status = Err(error);
+                        break;
+                    },
+                    None => {},
+                }
+            }
+        });
+        status?;
+        let snapshot: AfbPersistSnapshot = (shared, apis);
+
+        // session values may hold credentials, snapshot is private to binder user
+        let tmpname = format!("{}.tmp", self.path);
+        let _ = std::fs::remove_file(&tmpname);
+        let file = match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&tmpname)
+        {
+            Ok(file) => file,
+            Err(error) => {
+                return afb_error!("session-persist", "fail to create {} {}", tmpname, error)
+            },
+        };
+        let mut writer = std::io::BufWriter::new(file);
+        let status = match self.format {
+            AfbSessionFormat::Json => {
+                serde_json::to_writer(&mut writer, &snapshot).map_err(|error| error.to_string())
+            },
+            AfbSessionFormat::Cbor => {
+                ciborium::into_writer(&snapshot, &mut writer).map_err(|error| error.to_string())
+            },
+        };
+        if let Err(error) = status {
+            return afb_error!("session-persist", "fail to write {} {}", tmpname, error);
+        }
+        let file = match writer.into_inner() {
+            Ok(file) => file,
+            Err(error) => {
+                return afb_error!("session-persist", "fail to write {} {}", tmpname, error)
+            },
+        };
+        if let Err(error) = file.sync_all() {
+            return afb_error!("session-persist", "fail to sync {} {}", tmpname, error);
+        }
+        if let Err(error) = std::fs::rename(&tmpname, &self.path) {
+            return afb_error!("session-persist", "fail to rename {} {}", tmpname, error);
+        }
+        *saved_apis = snapshot.1;
+        if borrowed {
+            mark_dirty();
+        }
+        Ok(())
+    }
+
+    // reattach snapshot values, unknown or broken entries are logged and skipped. An unreadable
+    // snapshot is moved aside and sessions start empty.
+    #[track_caller]
+    pub(crate) fn restore(&self) -> Result<usize, AfbError> {
+        let file = match std::fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(error) => {
+                return afb_error!("session-persist", "fail to open {} {}", self.path, error)
+            },
+        };
+        let reader = std::io::BufReader::new(file);
+        let snapshot = match self.format {
+            AfbSessionFormat::Json => serde_json::from_reader::<_, AfbPersistSnapshot>(reader)
+                .map_err(|error| error.to_string()),
+            AfbSessionFormat::Cbor => ciborium::from_reader::<AfbPersistSnapshot, _>(reader)
+                .map_err(|error| error.to_string()),
+        };
+        let (shared, apis) = match snapshot {
+            Ok(snapshot) => snapshot,
+            Err(error) => {
+                let corrupt = format!("{}.corrupt", self.path);
+                let moved = std::fs::rename(&self.path, &corrupt);
+                afb_log_msg!(
+                    Error,
+                    None,
+                    "session-persist fail to parse {} {}, starting empty (moved:{})",
+                    self.path,
+                    error,
+                    moved.is_ok()
+                );
+                return Ok(0);
+            },
+        };
+
+        let mut count = 0;
+        let mut saved_apis = self.saved_apis.lock().unwrap_or_else(PoisonError::into_inner);
+        for (api, sessions) in apis {
+            for (uuid, jvalues) in sessions {
+                for (key, jvalue) in jvalues {
+                    let ptype = match self.api_types.iter().find(|ptype| ptype.key == key) {
+                        Some(ptype) => ptype,
+                        None => {
+                            afb_log_msg!(
+                                Warning,
+                                None,
+                                "session-persist api:{} session:{} unknown type:{}",
+                                api,
+                                uuid,
+                                key
+                            );
+                            continue;
+                        },
+                    };
+                    match (ptype.restore)(jvalue.clone()) {
+                        Ok(inner) => {
+                            api_session_restore(api.clone(), uuid.clone(), inner);
+                            saved_apis
+                                .entry(api.clone())
+                                .or_default()
+                                .insert(uuid.clone(), BTreeMap::from([(key, jvalue)]));
+                            count += 1;
+                        },
+                        Err(error) => {
+                            afb_log_msg!(Warning, None, &error);
+                        },
+                    }
+                }
+            }
+        }
+        let now = unix_now();
+        for (uuid, (created, jvalues)) in shared {
+            let mut values = Vec::new();
+            for (key, jvalue) in jvalues {
+                let ptype = match self.types.iter().find(|ptype| ptype.key == key) {
+                    Some(ptype) => ptype,
+                    None => {
+                        afb_log_msg!(
+                            Warning,
+                            None,
+                            "session-persist session:{} unknown type:{}",
+                            uuid,
+                            key
+                        );
+                        continue;
+                    },
+                };
+                match (ptype.restore)(jvalue) {
+                    Ok(slot) => values.push((ptype.type_id, slot)),
+                    Err(error) => {
+                        afb_log_msg!(Warning, None, &error);
+                    },
+                }
+            }
+            if !values.is_empty() {
+                let age = now.saturating_sub(Duration::from_secs(created));
+                store_restore(uuid, age, values);
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+// one save job queued at a time, binding exit flush and save job write in turn
+static AFB_SESSION_SAVING: AtomicBool = AtomicBool::new(false);
+static AFB_SESSION_SAVE_LOCK: Mutex<()> = Mutex::new(());
+
+// save store when it changed since last snapshot, failed saves are retried
+pub(crate) fn afb_session_flush() {
+    let persist = AFB_SESSION_PERSIST.load(Ordering::Acquire);
+    if persist.is_null() {
+        return;
+    }
+    let _lock = AFB_SESSION_SAVE_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    if !AFB_SESSION_DIRTY.swap(false, Ordering::AcqRel) {
+        return;
+    }
+    if let Err(error) = unsafe { &*persist }.save() {
+        mark_dirty();
+        afb_log_msg!(Error, None, &error);
+    }
+}
+
+// file write and sync run from their own job, not from the sweep timer
+pub(crate) fn afb_session_flush_post() {
+    if AFB_SESSION_PERSIST.load(Ordering::Acquire).is_null()
+        || !AFB_SESSION_DIRTY.load(Ordering::Acquire)
+        || AFB_SESSION_SAVING.swap(true, Ordering::AcqRel)
+    {
+        return;
+    }
+    let jobid = unsafe {
+        cglue::afb_job_post(
+            0,
+            0,
+            Some(api_session_save_cb),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if jobid <= 0 {
+        AFB_SESSION_SAVING.store(false, Ordering::Release);
+        afb_log_msg!(Error, None, "session-persist fail to post save job");
+    }
+}
+
+/// Job callback saving sessions changed since last snapshot.
+///
+/// # Safety
+/// Posted by the session sweep with no userdata; an aborted job leaves saving to binding exit.
+#[no_mangle]
+pub unsafe extern "C" fn api_session_save_cb(signal: i32, _userdata: *mut std::os::raw::c_void) {
+    // changes done while saving queue a new job on next sweep tick
+    AFB_SESSION_SAVING.store(false, Ordering::Release);
+    if signal != 0 {
+        return;
+    }
+    if let Err(error) = afb_catch_panic("session-persist", afb_session_flush) {
+        mark_dirty();
+        afb_log_msg!(Error, None, &error);
+    }
+}
@@ -0,0 +1,1050 @@
+/*
+ * Copyright (C) 2015-2023 IoT.bzh Company
+ * Author: Fulup Ar Foll <fulup@iot.bzh>
+ *
+ * $RP_BEGIN_LICENSE$
+ * Commercial License Usage
+ *  Licensees holding valid commercial IoT.bzh licenses may use this file in
+ *  accordance with the commercial license agreement provided with the
+ *  Software or, alternatively, in accordance with the terms contained in
+ *  a written agreement between you and The IoT.bzh Company. For licensing terms
+ *  and conditions see https://www.iot.bzh/terms-conditions. For further
+ *  information use the contact form at https://www.iot.bzh/contact.
+ *
+ * GNU General Public License Usage
+ *  Alternatively, this file may be used under the terms of the GNU General
+ *  Public license version 3. This license is as published by the Free Software
+ *  Foundation and appearing in the file LICENSE.GPLv3 included in the packaging
+ *  of this file. Please review the following information to ensure the GNU
+ *  General Public License requirements will be met
+ *  https://www.gnu.org/licenses/gpl-3.0.html.
+ * $RP_END_LICENSE$
+ */
+
+// Typed client session values. libafb keeps one context per api and client session
+// (afb_req_context_*), this context holds the legacy AfbRqtSession object plus any number of
+// typed values keyed by their TypeId. Values are shared between concurrent requests of the
+// same session through an Arc<Mutex<T>> and dropped when libafb closes the session, or when
+// idle/lifetime timeouts set on the api context expire (checked on access and by the sweep).
+//
+// AfbSessionStore shares typed values between every api of the binding, keyed by libafb
+// session uuid. Each api context touching a shared session holds a reference on it, the shared
+// session closes when libafb closed the last of them, on explicit close, or when idle/lifetime
+// timeouts declared by its value types expire (checked by a periodic sweep timer).
+
+use crate::prelude::*;
+use std::any::TypeId;
+use std::collections::BTreeMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError, Weak};
+use std::time::{Duration, Instant};
+
+/// Shared sessions idle/lifetime check period.
+pub const AFB_SESSION_SWEEP_MS: u32 = 1000;
+
+// libafb context get/set are two calls, serialize context creation
+static AFB_SESSION_LOCK: Mutex<()> = Mutex::new(());
+
+// api contexts with idle/lifetime timeouts, checked by the sweep timer
+static AFB_SESSION_TIMED: Mutex<Vec<Weak<AfbRqtSessionWrap>>> = Mutex::new(Vec::new());
+
+type AfbSessionValue = Arc<dyn Any + Send + Sync>;
+
+// session context content, legacy object, typed values and shared uuid change together
+pub(crate) struct AfbRqtSessionState {
+    pub(crate) inner: Option<Box<dyn AfbRqtSession>>,
+    values: BTreeMap<TypeId, AfbSessionValue>,
+    // shared session uuid and generation this context is attached to
+    shared: Option<(String, u64)>,
+    created: Instant,
+    accessed: Instant,
+    idle: Option<Duration>,
+    lifetime: Option<Duration>,
+    // requests holding the legacy object as `&mut`, content stays until the last one is gone
+    borrowers: usize,
+    // libafb closed the context while borrowed, last borrower clears it
+    closed: bool,
+}
+
+// Legacy AfbRqtSession objects never required Send: libafb already handed their context to
+// whatever binder thread served the client. State is only reached under its mutex or through the
+// request borrow that keeps it in place, never from two threads at once.
+unsafe impl Send for AfbRqtSessionState {}
+
+impl AfbRqtSessionState {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.inner.is_none()
+            && self.values.is_empty()
+            && self.shared.is_none()
+            && self.idle.is_none()
+            && self.lifetime.is_none()
+    }
+}
+
+// Rust dynamic object are fat pointer and should be encapculated before passing to C. libafb
+// context holds one Arc reference, requests working on it hold their own.
+pub(crate) struct AfbRqtSessionWrap {
+    state: Mutex<AfbRqtSessionState>,
+}
+
+impl AfbRqtSessionWrap {
+    pub(crate) fn lock(&self) -> MutexGuard<'_, AfbRqtSessionState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn new() -> Self {
+        let now = Instant::now();
+        AfbRqtSessionWrap {
+            state: Mutex::new(AfbRqtSessionState {
+                inner: None,
+                values: BTreeMap::new(),
+                shared: None,
+                created: now,
+                accessed: now,
+                idle: None,
+                lifetime: None,
+                borrowers: 0,
+                closed: false,
+            }),
+        }
+    }
+
+    // libafb closes api session context
+    pub(crate) fn closing(&self) {
+        {
+            let mut state = self.lock();
+            if state.borrowers > 0 {
+                state.closed = true;
+                return;
+            }
+        }
+        self.clear(None);
+    }
+
+    // idle/lifetime timeout reached: context content is released, context restarts empty
+    // with the same timeouts
+    fn expire(&self, now: Instant) -> bool {
+        self.clear(Some(now))
+    }
+
+    // hooks run outside of state lock
+    fn clear(&self, expire: Option<Instant>) -> bool {
+        let (inner, values, shared) = {
+            let mut state = self.lock();
+            if let Some(now) = expire {
+                // legacy object is in use, checked again on next access or sweep
+                if state.borrowers > 0 {
+                    return false;
+                }
+                let reason = AfbSessionExpire::check(
+                    state.created,
+                    state.accessed,
+                    state.idle,
+                    state.lifetime,
+                    now,
+                );
+                if reason.is_none() {
+                    return false;
+                }
+                state.created = now;
+                state.accessed = now;
+            }
+            (state.inner.take(), std::mem::take(&mut state.values), state.shared.take())
+        };
+        if let Some(mut inner) = inner {
+            mark_dirty();
+            if let Err(error) = afb_catch_panic("session-closing", || inner.closing()) {
+                afb_log_msg!(Error, None, &error);
+            }
+        }
+        drop(values);
+        if let Some((uuid, generation)) = shared {
+            AfbSessionStore::detach(&uuid, generation);
+        }
+        true
+    }
+}
+
+// a request handle holds the legacy session object as `&mut`, released with the handle
+pub(crate) struct AfbRqtSessionBorrow(Arc<AfbRqtSessionWrap>);
+
+impl Drop for AfbRqtSessionBorrow {
+    fn drop(&mut self) {
+        let closed = {
+            let mut state = self.0.lock();
+            state.borrowers -= 1;
+            state.borrowers == 0 && std::mem::take(&mut state.closed)
+        };
+        if closed {
+            self.0.clear(None);
+        }
+    }
+}
+
+impl AfbRequest {
+    // legacy object is handed out by this request handle, caller holds the session state lock
+    pub(crate) fn borrow_session(
+        &self,
+        wrap: &Arc<AfbRqtSessionWrap>,
+        state: &mut AfbRqtSessionState,
+    ) {
+        let mut borrows = self.session_borrows.borrow_mut();
+        if !borrows.iter().any(|borrow| Arc::ptr_eq(&borrow.0, wrap)) {
+            state.borrowers += 1;
+            borrows.push(AfbRqtSessionBorrow(wrap.clone()));
+        }
+    }
+
+    // requests other than this one holding the legacy object, caller holds the state lock
+    pub(crate) fn session_borrowers(
+        &self,
+        wrap: &Arc<AfbRqtSessionWrap>,
+        state: &AfbRqtSessionState,
+    ) -> usize {
+        let own = self.session_borrows.borrow().iter().any(|borrow| Arc::ptr_eq(&borrow.0, wrap));
+        state.borrowers - own as usize
+    }
+}
+
+// session a value handle belongs to, handle accesses count as session activity
+#[derive(Clone)]
+enum AfbSessionOwner {
+    Api(Weak<AfbRqtSessionWrap>),
+    Shared(Arc<AfbSessionAccess>),
+}
+
+static AFB_SESSION_EPOCH: OnceLock<Instant> = OnceLock::new();
+
+// shared session last access, nanoseconds since AFB_SESSION_EPOCH. Value handles update it
+// without taking the store lock.
+struct AfbSessionAccess(AtomicU64);
+
+impl AfbSessionAccess {
+    fn new(now: Instant) -> Self {
+        let access = AfbSessionAccess(AtomicU64::new(0));
+        access.touch(now);
+        access
+    }
+
+    fn touch(&self, now: Instant) {
+        let epoch = *AFB_SESSION_EPOCH.get_or_init(|| now);
+        let nanos = now.saturating_duration_since(epoch).as_nanos() as u64;
+        self.0.fetch_max(nanos, Ordering::Relaxed);
+    }
+
+    fn get(&self) -> Instant {
+        let epoch = *AFB_SESSION_EPOCH.get_or_init(Instant::now);
+        epoch + Duration::from_nanos(self.0.load(Ordering::Relaxed))
+    }
+}
+
+/// Typed value attached to a client session, clones share the same value.
+pub struct AfbSession<T> {
+    value: Arc<Mutex<T>>,
+    owner: AfbSessionOwner,
+}
+
+impl<T> Clone for AfbSession<T> {
+    fn clone(&self) -> Self {
+        AfbSession { value: self.value.clone(), owner: self.owner.clone() }
+    }
+}
+
+/// Exclusive access to a session value, a write access marks shared sessions for persistence.
+pub struct AfbSessionGuard<'a, T> {
+    guard: MutexGuard<'a, T>,
+    shared: bool,
+    written: bool,
+}
+
+impl<T> Deref for AfbSessionGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for AfbSessionGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.written = true;
+        &mut self.guard
+    }
+}
+
+impl<T> Drop for AfbSessionGuard<'_, T> {
+    fn drop(&mut self) {
+        if self.shared && self.written {
+            mark_dirty();
+        }
+    }
+}
+
+impl<T> AfbSession<T> {
+    /// Exclusive access to session value, other requests of the same session wait. Access
+    /// refreshes session idle timeout.
+    pub fn lock(&self) -> AfbSessionGuard<'_, T> {
+        self.touch();
+        AfbSessionGuard {
+            guard: self.value.lock().unwrap_or_else(PoisonError::into_inner),
+            shared: matches!(self.owner, AfbSessionOwner::Shared(_)),
+            written: false,
+        }
+    }
+
+    fn touch(&self) {
+        let now = Instant::now();
+        match &self.owner {
+            AfbSessionOwner::Api(wrap) => {
+                if let Some(wrap) = wrap.upgrade() {
+                    wrap.lock().accessed = now;
+                }
+            },
+            AfbSessionOwner::Shared(accessed) => accessed.touch(now),
+        }
+    }
+
+    pub fn with<R>(&self, callback: impl FnOnce(&mut T) -> R) -> R {
+        callback(&mut self.lock())
+    }
+}
+
+impl AfbRequest {
+    // run `callback` on libafb session context, optionally creating an empty one. Session lock is
+    // held meanwhile: another request of the same session cannot drop the context in between.
+    // access is counted as session activity, an expired context is cleared first.
+    pub(crate) fn with_session_wrap<R>(
+        &self,
+        create: bool,
+        callback: impl FnOnce(&Arc<AfbRqtSessionWrap>) -> R,
+    ) -> Option<R> {
+        // closing hooks of expired content run without session lock
+        let now = Instant::now();
+        let wrap = {
+            let _lock = AFB_SESSION_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+            self.get_session_wrap()
+        };
+        if let Some(wrap) = wrap {
+            wrap.expire(now);
+        }
+
+        let _lock = AFB_SESSION_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        let wrap = if let Some(wrap) = self.get_session_wrap() {
+            wrap
+        } else if create {
+            let wrap = Arc::new(AfbRqtSessionWrap::new());
+            let context = Arc::into_raw(wrap.clone()) as *mut std::os::raw::c_void;
+            let status = unsafe {
+                cglue::afb_req_context_set(
+                    self.rqtv4_handle(),
+                    context,
+                    Some(free_session_cb),
+                    context,
+                )
+            };
+            if status < 0 {
+                drop(unsafe { Arc::from_raw(context as *const AfbRqtSessionWrap) });
+                return None;
+            }
+            wrap
+        } else {
+            return None;
+        };
+        wrap.lock().accessed = now;
+        Some(callback(&wrap))
+    }
+
+    // own reference on libafb session context, caller holds session lock
+    fn get_session_wrap(&self) -> Option<Arc<AfbRqtSessionWrap>> {
+        let mut context = std::ptr::null_mut::<std::os::raw::c_void>();
+        let status = unsafe { cglue::afb_req_context_get(self.rqtv4_handle(), &mut context) };
+        if status < 0 || context.is_null() {
+            return None;
+        }
+        // libafb may free its context while caller works on it
+        let context = context as *const AfbRqtSessionWrap;
+        unsafe {
+            Arc::increment_strong_count(context);
+            Some(Arc::from_raw(context))
+        }
+    }
+
+    /// Expire this api session context `idle` after its last access or `lifetime` after its
+    /// creation: legacy session object `closing` runs, typed values are dropped and the context
+    /// restarts empty. `None` disables the corresponding timeout.
+    #[track_caller]
+    pub fn session_set_timeout(
+        &self,
+        idle: Option<Duration>,
+        lifetime: Option<Duration>,
+    ) -> Result<(), AfbError> {
+        let done = self.with_session_wrap(true, |wrap| {
+            let mut state = wrap.lock();
+            state.idle = idle;
+            state.lifetime = lifetime;
+            let weak = Arc::downgrade(wrap);
+            let mut timed = AFB_SESSION_TIMED.lock().unwrap_or_else(PoisonError::into_inner);
+            if !timed.iter().any(|timed| timed.ptr_eq(&weak)) {
+                timed.push(weak);
+            }
+        });
+        match done {
+            Some(()) => {
+                if idle.is_some() || lifetime.is_some() {
+                    start_sweep();
+                }
+                Ok(())
+            },
+            None => afb_error!("rqt-session-create", "request fail to create session"),
+        }
+    }
+
+    /// Session value of type `T`, error when not defined.
+    #[track_caller]
+    pub fn session<T>(&self) -> Result<AfbSession<T>, AfbError>
+    where
+        T: Send + 'static,
+    {
+        let value = self
+            .with_session_wrap(false, |wrap| {
+                let value = wrap.lock().values.get(&TypeId::of::<T>()).cloned();
+                value.map(|value| (value, Arc::downgrade(wrap)))
+            })
+            .flatten();
+        match value.map(|(value, wrap)| (value.downcast::<Mutex<T>>(), wrap)) {
+            Some((Ok(value), wrap)) => Ok(AfbSession { value, owner: AfbSessionOwner::Api(wrap) }),
+            _ => afb_error!(
+                "rqt-session-missing",
+                "session <{}> not defined",
+                std::any::type_name::<T>()
+            ),
+        }
+    }
+
+    /// Session value of type `T`, created with `init` when not defined yet.
+    #[track_caller]
+    pub fn session_or_insert_with<T, F>(&self, init: F) -> Result<AfbSession<T>, AfbError>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T,
+    {
+        if let Ok(session) = self.session::<T>() {
+            return Ok(session);
+        }
+        // user init runs without session lock, a concurrent request inserting first wins
+        let value: AfbSessionValue = Arc::new(Mutex::new(init()));
+        let (value, wrap) = match self.with_session_wrap(true, |wrap| {
+            let value = wrap.lock().values.entry(TypeId::of::<T>()).or_insert(value).clone();
+            (value, Arc::downgrade(wrap))
+        }) {
+            Some(value) => value,
+            None => return afb_error!("rqt-session-create", "request fail to create session"),
+        };
+        match value.downcast::<Mutex<T>>() {
+            Ok(value) => Ok(AfbSession { value, owner: AfbSessionOwner::Api(wrap) }),
+            Err(_) => {
+                afb_error!("session-any-cast", "fail to restore <{}>", std::any::type_name::<T>())
+            },
+        }
+    }
+
+    /// Remove session value of type `T` and return it, `None` when not defined. Fails when
+    /// another request still holds an `AfbSession<T>` on it.
+    #[track_caller]
+    pub fn session_take<T>(&self) -> Result<Option<T>, AfbError>
+    where
+        T: Send + 'static,
+    {
+        let value = self
+            .with_session_wrap(false, |wrap| wrap.lock().values.remove(&TypeId::of::<T>()))
+            .flatten();
+        let value = match value {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        let value = match value.downcast::<Mutex<T>>() {
+            Ok(value) => value,
+            Err(_) => {
+                return afb_error!(
+                    "session-any-cast",
+                    "fail to restore <{}>",
+                    std::any::type_name::<T>()
+                )
+            },
+        };
+        match Arc::try_unwrap(value) {
+            Ok(value) => Ok(Some(value.into_inner().unwrap_or_else(PoisonError::into_inner))),
+            Err(value) => {
+                // still in use, keep it in session
+                self.with_session_wrap(false, |wrap| {
+                    wrap.lock().values.insert(TypeId::of::<T>(), value)
+                });
+                afb_error!(
+                    "rqt-session-busy",
+                    "session <{}> still in use",
+                    std::any::type_name::<T>()
+                )
+            },
+        }
+    }
+}
+
+/// Value stored in binding-wide shared sessions, `closing` is called when the session closes.
+/// A session holding this type expires after `IDLE_TIMEOUT` without access or `LIFETIME` after
+/// its creation, shortest value wins when several types are stored.
+pub trait AfbSessionData: Send + 'static {
+    const IDLE_TIMEOUT: Option<Duration> = None;
+    const LIFETIME: Option<Duration> = None;
+    fn closing(&mut self, _uuid: &str) {}
+}
+
+/// Why a shared session was closed, given to `on_expire` callbacks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AfbSessionExpire {
+    Closed,
+    Idle,
+    Lifetime,
+}
+
+impl AfbSessionExpire {
+    /// Expiry reason at `now` of a session created and last accessed at given instants, `None`
+    /// while alive. Lifetime wins over idle when both are reached.
+    pub fn check(
+        created: Instant,
+        accessed: Instant,
+        idle: Option<Duration>,
+        lifetime: Option<Duration>,
+        now: Instant,
+    ) -> Option<AfbSessionExpire> {
+        if lifetime.is_some_and(|lifetime| now.duration_since(created) >= lifetime) {
+            Some(AfbSessionExpire::Lifetime)
+        } else if idle.is_some_and(|idle| now.duration_since(accessed) >= idle) {
+            Some(AfbSessionExpire::Idle)
+        } else {
+            None
+        }
+    }
+}
+
+// type erased shared value keeping access to closing hook
+pub(crate) trait AfbSessionSlot: Any + Send + Sync {
+    fn closing(&self, uuid: &str);
+    fn timeouts(&self) -> (Option<Duration>, Option<Duration>);
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync>;
+}
+
+impl<T: AfbSessionData> AfbSessionSlot for Mutex<T> {
+    fn closing(&self, uuid: &str) {
+        self.lock().unwrap_or_else(PoisonError::into_inner).closing(uuid)
+    }
+    fn timeouts(&self) -> (Option<Duration>, Option<Duration>) {
+        (T::IDLE_TIMEOUT, T::LIFETIME)
+    }
+    fn into_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+}
+
+type AfbSessionExpireCb = Arc<dyn Fn(&str, AfbSessionExpire) + Send + Sync>;
+
+static AFB_SESSION_EXPIRE_ID: AtomicU64 = AtomicU64::new(0);
+
+/// `AfbSessionStore::on_expire` registration, dropping it leaves the callback registered.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AfbSessionExpireHandle(u64);
+
+impl AfbSessionExpireHandle {
+    /// Remove callback, false when already removed. A session closing meanwhile may still call it.
+    pub fn unregister(self) -> bool {
+        let mut callbacks = AFB_SESSION_EXPIRE.lock().unwrap_or_else(PoisonError::into_inner);
+        let count = callbacks.len();
+        callbacks.retain(|(id, _)| *id != self.0);
+        callbacks.len() != count
+    }
+}
+
+struct AfbSharedSession {
+    values: BTreeMap<TypeId, Arc<dyn AfbSessionSlot>>,
+    apis: usize,
+    // a session closed then reopened with the same uuid gets a new generation, api contexts
+    // attached to the previous one attach again
+    generation: u64,
+    created: Instant,
+    accessed: Arc<AfbSessionAccess>,
+}
+
+static AFB_SESSION_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+impl Default for AfbSharedSession {
+    fn default() -> Self {
+        let now = Instant::now();
+        AfbSharedSession {
+            values: BTreeMap::new(),
+            apis: 0,
+            generation: AFB_SESSION_GENERATION.fetch_add(1, Ordering::Relaxed) + 1,
+            created: now,
+            accessed: Arc::new(AfbSessionAccess::new(now)),
+        }
+    }
+}
+
+impl AfbSharedSession {
+    // expiry reason at `now`, None while session is alive
+    fn expired(&self, now: Instant) -> Option<AfbSessionExpire> {
+        let idle = self.values.values().filter_map(|slot| slot.timeouts().0).min();
+        let lifetime = self.values.values().filter_map(|slot| slot.timeouts().1).min();
+        AfbSessionExpire::check(self.created, self.accessed.get(), idle, lifetime, now)
+    }
+
+    fn need_sweep(&self) -> bool {
+        self.values.values().any(|slot| slot.timeouts() != (None, None))
+    }
+}
+
+static AFB_SESSION_SWEEP: AtomicBool = AtomicBool::new(false);
+
+// shared sessions changed since last persistence snapshot
+pub(crate) static AFB_SESSION_DIRTY: AtomicBool = AtomicBool::new(false);
+
+// sweep timer starts with the first session value declaring a timeout (or with persistence)
+pub(crate) fn start_sweep() {
+    if AFB_SESSION_SWEEP.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    let timer = AfbTimer::new("session-sweep")
+        .set_info("shared sessions idle/lifetime expiry")
+        .set_period(AFB_SESSION_SWEEP_MS)
+        .set_callback(|_timer, _decount, _ctx| {
+            AfbSessionStore::sweep();
+            Ok(())
+        })
+        .start();
+    if let Err(error) = timer {
+        AFB_SESSION_SWEEP.store(false, Ordering::Release);
+        afb_log_msg!(Error, None, &error);
+    }
+}
+
+static AFB_SESSION_STORE: Mutex<BTreeMap<String, AfbSharedSession>> = Mutex::new(BTreeMap::new());
+static AFB_SESSION_EXPIRE: Mutex<Vec<(u64, AfbSessionExpireCb)>> = Mutex::new(Vec::new());
+
+fn get_store() -> MutexGuard<'static, BTreeMap<String, AfbSharedSession>> {
+    AFB_SESSION_STORE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// a persisted session value was added, removed or written
+pub(crate) fn mark_dirty() {
+    AFB_SESSION_DIRTY.store(true, Ordering::Release);
+}
+
+#[cfg(feature = "session-persist")]
+pub(crate) type AfbSessionSlots = Vec<(TypeId, Arc<dyn AfbSessionSlot>)>;
+
+// shared values snapshot with session age, value locks are taken by caller once store lock is
+// released
+#[cfg(feature = "session-persist")]
+pub(crate) fn store_slots() -> Vec<(String, Duration, AfbSessionSlots)> {
+    let now = Instant::now();
+    let store = AFB_SESSION_STORE.lock().unwrap_or_else(PoisonError::into_inner);
+    store
+        .iter()
+        .map(|(uuid, session)| {
+            let values =
+                session.values.iter().map(|(type_id, slot)| (*type_id, slot.clone())).collect();
+            (uuid.clone(), now.duration_since(session.created), values)
+        })
+        .collect()
+}
+
+// reattach values restored from persistence, live ones win. A session created by restore keeps
+// its saved age, lifetime counts from its first creation.
+#[cfg(feature = "session-persist")]
+pub(crate) fn store_restore(uuid: String, age: Duration, values: AfbSessionSlots) {
+    let sweep = {
+        let mut store = get_store();
+        let restored = !store.contains_key(&uuid);
+        let session = store.entry(uuid).or_default();
+        if restored {
+            session.created = session.created.checked_sub(age).unwrap_or(session.created);
+        }
+        for (type_id, slot) in values {
+            session.values.entry(type_id).or_insert(slot);
+        }
+        session.need_sweep()
+    };
+    if sweep {
+        start_sweep();
+    }
+}
+
+// legacy api session objects tracked for persistence: api uid, session uuid and context
+#[cfg(feature = "session-persist")]
+type AfbApiSessionRef = (&'static str, String, Weak<AfbRqtSessionWrap>);
+
+#[cfg(feature = "session-persist")]
+static AFB_API_SESSIONS: Mutex<Vec<AfbApiSessionRef>> = Mutex::new(Vec::new());
+
+// restored legacy objects waiting for their client, keyed by api uid and session uuid
+#[cfg(feature = "session-persist")]
+type AfbApiSessionRestored = BTreeMap<(String, String), Box<dyn AfbRqtSession + Send>>;
+
+#[cfg(feature = "session-persist")]
+static AFB_API_SESSIONS_RESTORED: Mutex<AfbApiSessionRestored> = Mutex::new(BTreeMap::new());
+
+// visit live legacy api session objects under their context lock, then restored ones not
+// claimed yet. Objects a request holds as `&mut` are not read: `None` is visited instead.
+#[cfg(feature = "session-persist")]
+pub(crate) fn api_sessions_visit(
+    mut visit: impl FnMut(&str, &str, Option<&mut dyn AfbRqtSession>),
+) {
+    let contexts: Vec<_> = {
+        let mut contexts = AFB_API_SESSIONS.lock().unwrap_or_else(PoisonError::into_inner);
+        contexts.retain(|(_, _, wrap)| wrap.strong_count() > 0);
+        contexts
+            .iter()
+            .filter_map(|(api, uuid, wrap)| Some((*api, uuid.clone(), wrap.upgrade()?)))
+            .collect()
+    };
+    for (api, uuid, wrap) in contexts {
+        let mut state = wrap.lock();
+        let borrowed = state.borrowers > 0;
+        if let Some(inner) = state.inner.as_mut() {
+            visit(api, &uuid, if borrowed { None } else { Some(inner.as_mut()) });
+        }
+    }
+    let mut restored = AFB_API_SESSIONS_RESTORED.lock().unwrap_or_else(PoisonError::into_inner);
+    for ((api, uuid), inner) in restored.iter_mut() {
+        visit(api, uuid, Some(inner.as_mut()));
+    }
+}
+
+// legacy api session object read from persistence, attached on first access of its client
+#[cfg(feature = "session-persist")]
+pub(crate) fn api_session_restore(api: String, uuid: String, inner: Box<dyn AfbRqtSession + Send>) {
+    AFB_API_SESSIONS_RESTORED
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .insert((api, uuid), inner);
+}
+
+fn downcast_slot<T: AfbSessionData>(
+    slot: Arc<dyn AfbSessionSlot>,
+) -> Result<Arc<Mutex<T>>, AfbError> {
+    match slot.into_any().downcast::<Mutex<T>>() {
+        Ok(value) => Ok(value),
+        Err(_) => {
+            afb_error!("session-any-cast", "fail to restore <{}>", std::any::type_name::<T>())
+        },
+    }
+}
+
+/// Binding-wide session store keyed by libafb session uuid, shared by every api of the binding.
+pub struct AfbSessionStore {}
+
+impl AfbSessionStore {
+    /// Shared value of type `T` for session `uuid`, `None` when not defined.
+    pub fn get<T: AfbSessionData>(uuid: &str) -> Option<AfbSession<T>> {
+        let (slot, accessed) = {
+            let mut store = get_store();
+            let session = store.get_mut(uuid)?;
+            session.accessed.touch(Instant::now());
+            (session.values.get(&TypeId::of::<T>())?.clone(), session.accessed.clone())
+        };
+        let owner = AfbSessionOwner::Shared(accessed);
+        downcast_slot::<T>(slot).ok().map(|value| AfbSession { value, owner })
+    }
+
+    /// Shared value of type `T` for session `uuid`, created with `init` when not defined yet.
+    #[track_caller]
+    pub fn get_or_insert_with<T, F>(uuid: &str, init: F) -> Result<AfbSession<T>, AfbError>
+    where
+        T: AfbSessionData,
+        F: FnOnce() -> T,
+    {
+        if let Some(session) = AfbSessionStore::get::<T>(uuid) {
+            return Ok(session);
+        }
+        // user init runs without store lock, a concurrent insert done first wins
+        let value: Arc<dyn AfbSessionSlot> = Arc::new(Mutex::new(init()));
+        let (slot, accessed, sweep) = {
+            let mut store = get_store();
+            let session = store.entry(uuid.to_string()).or_default();
+            session.accessed.touch(Instant::now());
+            let slot = session.values.entry(TypeId::of::<T>()).or_insert(value).clone();
+            (slot, session.accessed.clone(), session.need_sweep())
+        };
+        mark_dirty();
+        if sweep {
+            start_sweep();
+        }
+        let owner = AfbSessionOwner::Shared(accessed);
+        Ok(AfbSession { value: downcast_slot::<T>(slot)?, owner })
+    }
+
+    /// Set (or replace) shared value of type `T` for session `uuid`.
+    pub fn insert<T: AfbSessionData>(uuid: &str, value: T) -> AfbSession<T> {
+        let value = Arc::new(Mutex::new(value));
+        let (previous, accessed, sweep) = {
+            let mut store = get_store();
+            let session = store.entry(uuid.to_string()).or_default();
+            session.accessed.touch(Instant::now());
+            let previous = session.values.insert(TypeId::of::<T>(), value.clone());
+            (previous, session.accessed.clone(), session.need_sweep())
+        };
+        mark_dirty();
+        if let Some(previous) = previous {
+            previous.closing(uuid);
+        }
+        if sweep {
+            start_sweep();
+        }
+        AfbSession { value, owner: AfbSessionOwner::Shared(accessed) }
+    }
+
+    /// Remove shared value of type `T` from session `uuid` without calling its `closing`.
+    #[track_caller]
+    pub fn take<T: AfbSessionData>(uuid: &str) -> Result<Option<T>, AfbError> {
+        let slot = match get_store().get_mut(uuid) {
+            Some(session) => session.values.remove(&TypeId::of::<T>()),
+            None => None,
+        };
+        let value = match slot {
+            Some(slot) => downcast_slot::<T>(slot)?,
+            None => return Ok(None),
+        };
+        match Arc::try_unwrap(value) {
+            Ok(value) => {
+                mark_dirty();
+                Ok(Some(value.into_inner().unwrap_or_else(PoisonError::into_inner)))
+            },
+            Err(value) => {
+                // still in use, keep it in session
+                if let Some(session) = get_store().get_mut(uuid) {
+                    session.values.insert(TypeId::of::<T>(), value);
+                }
+                afb_error!(
+                    "rqt-session-busy",
+                    "session <{}> still in use",
+                    std::any::type_name::<T>()
+                )
+            },
+        }
+    }
+
+    /// Callback run with session uuid and reason each time a shared session closes, until
+    /// returned handle unregisters it.
+    pub fn on_expire<F>(callback: F) -> AfbSessionExpireHandle
+    where
+        F: Fn(&str, AfbSessionExpire) + Send + Sync + 'static,
+    {
+        let id = AFB_SESSION_EXPIRE_ID.fetch_add(1, Ordering::Relaxed) + 1;
+        let callback: AfbSessionExpireCb = Arc::new(callback);
+        AFB_SESSION_EXPIRE
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push((id, callback));
+        AfbSessionExpireHandle(id)
+    }
+
+    /// Close shared session `uuid`: expire callbacks then `closing` on every value.
+    pub fn close(uuid: &str) {
+        let session = get_store().remove(uuid);
+        if let Some(session) = session {
+            mark_dirty();
+            AfbSessionStore::expire(uuid, session, AfbSessionExpire::Closed);
+        }
+    }
+
+    /// Close shared sessions whose idle timeout or lifetime expired and clear expired api
+    /// session contexts, run periodically once a timeout is declared.
+    pub fn sweep() {
+        let now = Instant::now();
+        // api contexts released by libafb are forgotten, expiry runs outside registry lock
+        let timed: Vec<_> = {
+            let mut timed = AFB_SESSION_TIMED.lock().unwrap_or_else(PoisonError::into_inner);
+            timed.retain(|wrap| wrap.strong_count() > 0);
+            timed.iter().filter_map(Weak::upgrade).collect()
+        };
+        for wrap in timed {
+            wrap.expire(now);
+        }
+
+        let mut expired = Vec::new();
+        get_store().retain(|uuid, session| match session.expired(now) {
+            Some(reason) => {
+                expired.push((uuid.clone(), std::mem::take(session), reason));
+                false
+            },
+            None => true,
+        });
+        if !expired.is_empty() {
+            mark_dirty();
+        }
+        for (uuid, session, reason) in expired {
+            AfbSessionStore::expire(&uuid, session, reason);
+        }
+        #[cfg(feature = "session-persist")]
+        afb_session_flush_post();
+    }
+
+    pub fn get_uuids() -> Vec<String> {
+        get_store().keys().cloned().collect()
+    }
+
+    // run outside store lock, callbacks may use the store
+    fn expire(uuid: &str, session: AfbSharedSession, reason: AfbSessionExpire) {
+        let callbacks = AFB_SESSION_EXPIRE.lock().unwrap_or_else(PoisonError::into_inner).clone();
+        for (_, callback) in callbacks {
+            if let Err(error) = afb_catch_panic("session-expire", || callback(uuid, reason)) {
+                afb_log_msg!(Error, None, &error);
+            }
+        }
+        for slot in session.values.values() {
+            if let Err(error) = afb_catch_panic("session-closing", || slot.closing(uuid)) {
+                afb_log_msg!(Error, None, &error);
+            }
+        }
+    }
+
+    // one more api context references shared session, returns session generation
+    fn attach(uuid: &str) -> u64 {
+        let mut store = get_store();
+        let session = store.entry(uuid.to_string()).or_default();
+        session.apis += 1;
+        session.generation
+    }
+
+    // false once session was closed (explicitly or by sweep) since api context attached
+    fn is_attached(uuid: &str, generation: u64) -> bool {
+        get_store().get(uuid).is_some_and(|session| session.generation == generation)
+    }
+
+    // api context closed by libafb, last one closes shared session
+    fn detach(uuid: &str, generation: u64) {
+        let session = {
+            let mut store = get_store();
+            match store.get_mut(uuid) {
+                Some(session) if session.generation != generation => None,
+                Some(session) if session.apis > 1 => {
+                    session.apis -= 1;
+                    None
+                },
+                Some(_) => store.remove(uuid),
+                None => None,
+            }
+        };
+        if let Some(session) = session {
+            mark_dirty();
+            AfbSessionStore::expire(uuid, session, AfbSessionExpire::Closed);
+        }
+    }
+}
+
+impl AfbRequest {
+    // attach legacy session object restored for this api and client, if any
+    #[cfg(feature = "session-persist")]
+    pub(crate) fn claim_api_session(&self) {
+        if AFB_API_SESSIONS_RESTORED
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .is_empty()
+        {
+            return;
+        }
+        let uuid = match self.get_session_uuid() {
+            Ok(uuid) => uuid,
+            Err(_) => return,
+        };
+        let inner = AFB_API_SESSIONS_RESTORED
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&(self.get_api().get_uid().to_string(), uuid));
+        if let Some(inner) = inner {
+            self.with_session_wrap(true, |wrap| {
+                wrap.lock().inner.get_or_insert(inner);
+                self.track_api_session(wrap);
+            });
+        }
+    }
+
+    // legacy session object of this api context is saved by persistence
+    #[cfg(feature = "session-persist")]
+    pub(crate) fn track_api_session(&self, wrap: &Arc<AfbRqtSessionWrap>) {
+        let uuid = match self.get_session_uuid() {
+            Ok(uuid) => uuid,
+            Err(_) => return,
+        };
+        let weak = Arc::downgrade(wrap);
+        let mut contexts = AFB_API_SESSIONS.lock().unwrap_or_else(PoisonError::into_inner);
+        if !contexts.iter().any(|(_, _, context)| context.ptr_eq(&weak)) {
+            contexts.push((self.get_api().get_uid(), uuid, weak));
+        }
+    }
+
+    // shared session uuid, api context tracks it to release shared session on libafb close
+    #[track_caller]
+    fn get_shared_uuid(&self) -> Result<String, AfbError> {
+        let uuid = self.get_session_uuid()?;
+        let attached = self.with_session_wrap(true, |wrap| {
+            let mut state = wrap.lock();
+            let attached = match &state.shared {
+                Some((shared, generation)) => {
+                    *shared == uuid && AfbSessionStore::is_attached(&uuid, *generation)
+                },
+                None => false,
+            };
+            // first access, or shared session closed since then: attach to current one
+            if !attached {
+                let generation = AfbSessionStore::attach(&uuid);
+                state.shared = Some((uuid.clone(), generation));
+            }
+        });
+        match attached {
+            Some(()) => Ok(uuid),
+            None => afb_error!("rqt-session-create", "request fail to create session"),
+        }
+    }
+
+    /// Binding-wide shared value of type `T` for request client session.
+    #[track_caller]
+    pub fn shared_session<T: AfbSessionData>(&self) -> Result<AfbSession<T>, AfbError> {
+        let uuid = self.get_shared_uuid()?;
+        match AfbSessionStore::get::<T>(&uuid) {
+            Some(session) => Ok(session),
+            None => afb_error!(
+                "rqt-session-missing",
+                "shared session <{}> not defined",
+                std::any::type_name::<T>()
+            ),
+        }
+    }
+
+    /// Binding-wide shared value of type `T`, created with `init` when not defined yet.
+    #[track_caller]
+    pub fn shared_session_or_insert_with<T, F>(&self, init: F) -> Result<AfbSession<T>, AfbError>
+    where
+        T: AfbSessionData,
+        F: FnOnce() -> T,
+    {
+        let uuid = self.get_shared_uuid()?;
+        AfbSessionStore::get_or_insert_with(&uuid, init)
+    }
+
+    /// Close client session: libafb drops every api session context and binding-wide shared
+    /// session expires now.
+    #[track_caller]
+    pub fn session_close(&self) {
+        let uuid = self.get_session_uuid();
+        unsafe { cglue::afb_req_session_close(self.rqtv4_handle()) };
+        if let Ok(uuid) = uuid {
+            AfbSessionStore::close(&uuid);
+        }
+    }
+
+    /// Remove binding-wide shared value of type `T` and return it.
+    #[track_caller]
+    pub fn shared_session_take<T: AfbSessionData>(&self) -> Result<Option<T>, AfbError> {
+        let uuid = self.get_session_uuid()?;
+        AfbSessionStore::take::<T>(&uuid)
+    }
+}
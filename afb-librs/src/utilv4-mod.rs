@@ -24,10 +24,13 @@ use crate::prelude::*;
 
 use ::std::os::raw;
 use bitflags::bitflags;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
 use std::ffi::CStr;
 use std::ffi::CString;
 use std::fmt;
-use std::panic::Location;
+use std::panic::{AssertUnwindSafe, Location};
+use std::sync::{Mutex, Once, PoisonError};
 
 const MAX_ERROR_LEN: usize = 256;
 pub fn get_perror() -> String {
@@ -314,8 +317,7 @@ impl<'a> DoSendLog<&AfbEventMsg<'a>> for AfbLogMsg {
     }
 
     unsafe fn get_verbosity(event: &AfbEventMsg) -> u32 {
-        let handle = event.get_handler();
-        handle.get_verbosity()
+        event.get_verbosity()
     }
 }
 
@@ -362,7 +364,7 @@ impl DoSendLog<&AfbRequest> for AfbLogMsg {
         funcname: *const Cchar,
         format: *const Cchar,
     ) {
-        cglue::afb_req_verbose((*rqt).get_rqtv4(), level, file, line as i32, funcname, format)
+        cglue::afb_req_verbose((*rqt).rqtv4_handle(), level, file, line as i32, funcname, format)
     }
 
     unsafe fn get_verbosity(rqt: &AfbRequest) -> u32 {
@@ -597,6 +599,8 @@ impl AfbLogMsg {
 // AfbTimer callback api signature
 pub type TimerCallback =
     fn(timer: &AfbTimer, decount: u32, ctx: &AfbCtxData) -> Result<(), AfbError>;
+pub type TimerClosure =
+    Box<dyn Fn(&AfbTimer, u32, &AfbCtxData) -> Result<(), AfbError> + Send + Sync>;
 #[track_caller]
 fn timer_default_cb(timer: &AfbTimer, _decount: u32, _ctx: &AfbCtxData) -> Result<(), AfbError> {
     afb_error!("afb-default-cb", "uid:{} no timer callback defined", timer.get_uid())
@@ -625,7 +629,10 @@ pub unsafe extern "C" fn api_timers_cb(
 ) {
     // extract timer+api object from libafb internals
     let timer_ref = &mut *(userdata as *mut AfbTimer);
-    let result = (timer_ref.callback)(timer_ref, decount, &timer_ref.context);
+    let uid = timer_ref._uid;
+    let result =
+        afb_catch_panic(uid, || (timer_ref.callback)(timer_ref, decount, &timer_ref.context))
+            .and_then(|result| result);
 
     match result {
         Ok(()) => {},
@@ -646,6 +653,7 @@ pub unsafe extern "C" fn api_timers_cb(
 
     // clean callback control box
     if decount == 1 {
+        exit_untrack(timer_ref as *const _ as *const std::ffi::c_void);
         let _ctrlbox = unsafe { Box::from_raw(timer_ref) };
     }
 }
@@ -654,7 +662,7 @@ pub struct AfbTimer {
     _uid: &'static str,
     _timerv4: AfbTmrV4,
     info: &'static str,
-    callback: TimerCallback,
+    callback: TimerClosure,
     context: AfbCtxData,
     decount: u32,
     period: u32,
@@ -692,7 +700,7 @@ impl AfbTimer {
             // If `afb_timer_t` is a pointer type, prefer the null-pointer variant below.
             _timerv4: 0 as cglue::afb_timer_t, // backend handle (none yet)
             autounref: 0,                      // auto-unref flag (0: disabled)
-            callback: timer_default_cb,        // default callback invoked by the backend
+            callback: Box::new(timer_default_cb), // default callback invoked by the backend
             context: AfbCtxData::new(AFB_NO_DATA), // empty user context
         });
 
@@ -726,8 +734,21 @@ impl AfbTimer {
         self.autounref = value;
         self
     }
-    pub fn set_callback(&mut self, callback: TimerCallback) -> &mut Self {
-        self.callback = callback;
+    /// Tick handler, a `TimerCallback` fn reading `set_context` data or any matching closure.
+    pub fn set_callback<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&AfbTimer, u32, &AfbCtxData) -> Result<(), AfbError> + Send + Sync + 'static,
+    {
+        self.callback = Box::new(callback);
+        self
+    }
+
+    /// Tick handler without timer context, state is captured by the closure itself.
+    pub fn set_closure<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&AfbTimer, u32) -> Result<(), AfbError> + Send + Sync + 'static,
+    {
+        self.callback = Box::new(move |timer, decount, _ctx| callback(timer, decount));
         self
     }
 
@@ -762,6 +783,7 @@ impl AfbTimer {
         if status != 0 {
             return afb_error!(self._uid, "Afb_Timer creation fail");
         }
+        exit_track(AfbExitSlot::Timer(self));
         Ok(self)
     }
 
@@ -770,6 +792,7 @@ impl AfbTimer {
     }
 
     pub fn unref(&self) {
+        exit_untrack(self as *const _ as *const std::ffi::c_void);
         unsafe { cglue::afb_timer_unref(self._timerv4) };
     }
 
@@ -795,6 +818,8 @@ pub type JobCallback = fn(
     args: &AfbCtxData,
     ctx: &AfbCtxData,
 ) -> Result<(), AfbError>;
+pub type JobClosure =
+    Box<dyn Fn(&AfbSchedJob, i32, &AfbCtxData, &AfbCtxData) -> Result<(), AfbError> + Send + Sync>;
 #[track_caller]
 fn job_default_cb(
     job: &AfbSchedJob,
@@ -822,9 +847,14 @@ struct SchedJobV4 {
 /// - All invariants required by the surrounding FFI/libafb code must be preserved.
 #[no_mangle]
 pub unsafe extern "C" fn api_schedjob_cb(signal: i32, userdata: *mut std::os::raw::c_void) {
+    exit_untrack(userdata);
     let handle = Box::from_raw(userdata as *mut SchedJobV4);
     let job_ref = unsafe { &mut *(handle.job as *mut AfbSchedJob) };
-    let result = (job_ref.callback)(job_ref, signal, &handle.args, &job_ref.context);
+    let uid = job_ref._uid;
+    let result = afb_catch_panic(uid, || {
+        (job_ref.callback)(job_ref, signal, &handle.args, &job_ref.context)
+    })
+    .and_then(|result| result);
 
     match result {
         Ok(()) => {},
@@ -850,7 +880,7 @@ pub struct AfbSchedJob {
     group: usize,
     watchdog: i32,
     verbosity: u32,
-    callback: JobCallback,
+    callback: JobClosure,
     context: AfbCtxData,
 }
 
@@ -862,7 +892,7 @@ impl AfbSchedJob {
             group: 0,
             watchdog: 0,
             verbosity: 0,
-            callback: job_default_cb,
+            callback: Box::new(job_default_cb),
             context: AfbCtxData::new(AFB_NO_DATA),
         });
         Box::leak(job_box)
@@ -892,8 +922,25 @@ impl AfbSchedJob {
         self
     }
 
-    pub fn set_callback(&mut self, callback: JobCallback) -> &mut Self {
-        self.callback = callback;
+    /// Job handler receiving posted args then job context, as a `JobCallback` fn or a closure.
+    pub fn set_callback<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&AfbSchedJob, i32, &AfbCtxData, &AfbCtxData) -> Result<(), AfbError>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.callback = Box::new(callback);
+        self
+    }
+
+    /// Job handler without job context: only args given to `post` are passed, anything else
+    /// the job needs lives in the closure captures.
+    pub fn set_closure<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&AfbSchedJob, i32, &AfbCtxData) -> Result<(), AfbError> + Send + Sync + 'static,
+    {
+        self.callback = Box::new(move |job, signal, args, _ctx| callback(job, signal, args));
         self
     }
 
@@ -919,6 +966,9 @@ impl AfbSchedJob {
             args: AfbCtxData::new(args),
         }));
 
+        // track before posting, job may run before afb_job_post returns
+        exit_track(AfbExitSlot::JobPost(handle as *const std::ffi::c_void, 0));
+
         let jobv4 = unsafe {
             cglue::afb_job_post(
                 delay_ms,
@@ -929,8 +979,11 @@ impl AfbSchedJob {
             )
         };
         if jobv4 <= 0 {
+            exit_untrack(handle as *const std::ffi::c_void);
+            let _ = unsafe { Box::from_raw(handle) };
             return afb_error!(self._uid, "Job_post launch fail");
         }
+        exit_job_posted(handle as *const std::ffi::c_void, jobv4);
         Ok(jobv4)
     }
 
@@ -1090,6 +1143,8 @@ impl AfbPermission {
 // AfbEvtFdControl callback api signature
 pub type EvtFdCallback =
     fn(evfd: &AfbEvtFd, revents: u32, ctx: &AfbCtxData) -> Result<(), AfbError>;
+pub type EvtFdClosure =
+    Box<dyn Fn(&AfbEvtFd, u32, &AfbCtxData) -> Result<(), AfbError> + Send + Sync>;
 #[track_caller]
 fn evtfd_default_cb(evfd: &AfbEvtFd, _revents: u32, _ctx: &AfbCtxData) -> Result<(), AfbError> {
     afb_error!("afb-default-cb", "uid:{} no evtfd callback defined", evfd.get_uid())
@@ -1120,7 +1175,10 @@ pub unsafe extern "C" fn api_evtfd_cb(
     let evtfd_ref = &mut *(userdata as *mut AfbEvtFd);
 
     // call evtfd calback
-    let result = (evtfd_ref.callback)(evtfd_ref, revents, &evtfd_ref.context);
+    let uid = evtfd_ref.uid;
+    let result =
+        afb_catch_panic(uid, || (evtfd_ref.callback)(evtfd_ref, revents, &evtfd_ref.context))
+            .and_then(|result| result);
 
     match result {
         Ok(()) => {},
@@ -1141,6 +1199,7 @@ pub unsafe extern "C" fn api_evtfd_cb(
 
     // clean callback control box
     if (revents & AfbEvtFdPoll::RUP.bits()) != 0 || (revents & AfbEvtFdPoll::HUP.bits()) != 0 {
+        exit_untrack(evtfd_ref as *const _ as *const std::ffi::c_void);
         let _ctrlbox = Box::from_raw(evtfd_ref);
         cglue::afb_evfd_unref(efd);
     }
@@ -1165,7 +1224,7 @@ pub struct AfbEvtFd {
     efdv4: cglue::afb_evfd_t,
     fd: ::std::os::raw::c_int,
     events: u32,
-    callback: EvtFdCallback,
+    callback: EvtFdClosure,
     context: AfbCtxData,
     autounref: i32,
     autoclose: i32,
@@ -1177,7 +1236,7 @@ impl AfbEvtFd {
             uid,
             fd: 0,
             info: "",
-            callback: evtfd_default_cb,
+            callback: Box::new(evtfd_default_cb),
             context: AfbCtxData::new(AFB_NO_DATA),
             autounref: 0,
             autoclose: 0,
@@ -1216,8 +1275,21 @@ impl AfbEvtFd {
         self
     }
 
-    pub fn set_callback(&mut self, callback: EvtFdCallback) -> &mut Self {
-        self.callback = callback;
+    /// Poll events handler (`revents` mask), an `EvtFdCallback` fn or an equivalent closure.
+    pub fn set_callback<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&AfbEvtFd, u32, &AfbCtxData) -> Result<(), AfbError> + Send + Sync + 'static,
+    {
+        self.callback = Box::new(callback);
+        self
+    }
+
+    /// Poll events handler for closures that capture their fd state instead of `set_context`.
+    pub fn set_closure<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&AfbEvtFd, u32) -> Result<(), AfbError> + Send + Sync + 'static,
+    {
+        self.callback = Box::new(move |evtfd, revents, _ctx| callback(evtfd, revents));
         self
     }
 
@@ -1249,6 +1321,7 @@ impl AfbEvtFd {
         if status != 0 {
             return afb_error!(self.uid, "Afb_EvtFd creation fail");
         }
+        exit_track(AfbExitSlot::EvtFd(self));
         Ok(self)
     }
 
@@ -1257,6 +1330,7 @@ impl AfbEvtFd {
     }
 
     pub fn unref(&self) {
+        exit_untrack(self as *const _ as *const std::ffi::c_void);
         unsafe { cglue::afb_evfd_unref(self.efdv4) };
     }
 
@@ -1276,3 +1350,226 @@ impl AfbEvtFd {
         self.info
     }
 }
+
+// Afb exit tracker: objects handed to libafb are released when the last binding api exits
+// ------------------------
+type ExitHook = Box<dyn FnOnce() + Send>;
+
+enum AfbExitSlot {
+    Timer(*mut AfbTimer),
+    EvtFd(*mut AfbEvtFd),
+    JobPost(*const std::ffi::c_void, i32),
+    Event(&'static str, cglue::afb_event_t),
+    Hook(&'static str, ExitHook),
+}
+
+impl AfbExitSlot {
+    // libafb handle used to untrack the slot, hooks and events are only released on exit
+    fn handle(&self) -> Option<usize> {
+        match self {
+            AfbExitSlot::Timer(timer) => Some(*timer as usize),
+            AfbExitSlot::EvtFd(evtfd) => Some(*evtfd as usize),
+            AfbExitSlot::JobPost(handle, _) => Some(*handle as usize),
+            AfbExitSlot::Event(..) | AfbExitSlot::Hook(..) => None,
+        }
+    }
+}
+
+struct AfbExitTracker {
+    next: u64,
+    // registration order -> owner api (0 when created outside of any api callback) and object
+    slots: BTreeMap<u64, (usize, AfbExitSlot)>,
+    // tracked handle -> registration order
+    handles: BTreeMap<usize, u64>,
+}
+
+// slots are only touched behind tracker mutex, libafb handles are thread safe
+unsafe impl Send for AfbExitTracker {}
+
+static AFB_EXIT_TRACKER: Mutex<AfbExitTracker> =
+    Mutex::new(AfbExitTracker { next: 0, slots: BTreeMap::new(), handles: BTreeMap::new() });
+
+thread_local! {
+    // api whose control or verb callback runs on this thread, owner of objects created meanwhile
+    static AFB_EXIT_OWNER: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Objects tracked while this scope is alive are released when `apiv4` exits.
+pub(crate) struct AfbExitScope {
+    previous: usize,
+}
+
+impl AfbExitScope {
+    pub(crate) fn enter(apiv4: cglue::afb_api_t) -> Self {
+        AfbExitScope { previous: AFB_EXIT_OWNER.with(|owner| owner.replace(apiv4 as usize)) }
+    }
+}
+
+impl Drop for AfbExitScope {
+    fn drop(&mut self) {
+        AFB_EXIT_OWNER.with(|owner| owner.set(self.previous));
+    }
+}
+
+fn exit_track(slot: AfbExitSlot) {
+    let owner = AFB_EXIT_OWNER.with(|owner| owner.get());
+    let mut tracker = AFB_EXIT_TRACKER.lock().unwrap_or_else(PoisonError::into_inner);
+    let seq = tracker.next;
+    tracker.next += 1;
+    if let Some(handle) = slot.handle() {
+        tracker.handles.insert(handle, seq);
+    }
+    tracker.slots.insert(seq, (owner, slot));
+}
+
+fn exit_untrack(handle: *const std::ffi::c_void) {
+    let mut tracker = AFB_EXIT_TRACKER.lock().unwrap_or_else(PoisonError::into_inner);
+    if let Some(seq) = tracker.handles.remove(&(handle as usize)) {
+        tracker.slots.remove(&seq);
+    }
+}
+
+fn exit_job_posted(handle: *const std::ffi::c_void, jobid: i32) {
+    let mut tracker = AFB_EXIT_TRACKER.lock().unwrap_or_else(PoisonError::into_inner);
+    let seq = match tracker.handles.get(&(handle as usize)) {
+        Some(seq) => *seq,
+        None => return,
+    };
+    if let Some((_, AfbExitSlot::JobPost(_, slot_jobid))) = tracker.slots.get_mut(&seq) {
+        *slot_jobid = jobid;
+    }
+}
+
+pub(crate) fn exit_track_event(uid: &'static str, evtv4: cglue::afb_event_t) {
+    exit_track(AfbExitSlot::Event(uid, evtv4));
+}
+
+/// Register a cleanup hook called on exit, hooks and tracked timers, evtfds, pending jobs and
+/// events are released in reverse creation order. Objects created from an api callback are
+/// released when this api exits, others when the last binding api exits.
+pub fn afb_on_exit<F>(uid: &'static str, hook: F)
+where
+    F: FnOnce() + Send + 'static,
+{
+    exit_track(AfbExitSlot::Hook(uid, Box::new(hook)));
+}
+
+/// Release objects owned by an exiting api, `last` also releases objects without owner.
+/// libafb references are dropped and hooks called in reverse registration order, then
+/// timer and evtfd Rust objects are freed: their `&'static` handles must not be used after
+/// exit hooks.
+pub(crate) fn afb_exit_release(apiv4: cglue::afb_api_t, last: bool) {
+    // take slots out of the lock, released callbacks may untrack other slots
+    let slots: Vec<AfbExitSlot> = {
+        let mut tracker = AFB_EXIT_TRACKER.lock().unwrap_or_else(PoisonError::into_inner);
+        let released: Vec<u64> = tracker
+            .slots
+            .iter()
+            .filter(|(_, (owner, _))| *owner == apiv4 as usize || (last && *owner == 0))
+            .map(|(seq, _)| *seq)
+            .collect();
+        let mut slots = Vec::with_capacity(released.len());
+        for seq in released.into_iter().rev() {
+            if let Some((_, slot)) = tracker.slots.remove(&seq) {
+                if let Some(handle) = slot.handle() {
+                    tracker.handles.remove(&handle);
+                }
+                slots.push(slot);
+            }
+        }
+        slots
+    };
+
+    let mut leaked = Vec::new();
+    for slot in slots {
+        match slot {
+            AfbExitSlot::JobPost(_, jobid) => {
+                // aborted job callback still runs with a signal and frees its arguments
+                if jobid > 0 {
+                    unsafe { cglue::afb_job_abort(jobid) };
+                }
+            },
+            AfbExitSlot::Timer(timer) => {
+                let timer_ref = unsafe { &*timer };
+                afb_log_msg!(Debug, None, "exit release timer:{}", timer_ref._uid);
+                unsafe { cglue::afb_timer_unref(timer_ref._timerv4) };
+                leaked.push(slot);
+            },
+            AfbExitSlot::EvtFd(evtfd) => {
+                let evtfd_ref = unsafe { &*evtfd };
+                afb_log_msg!(Debug, None, "exit release evtfd:{}", evtfd_ref.uid);
+                unsafe { cglue::afb_evfd_unref(evtfd_ref.efdv4) };
+                leaked.push(slot);
+            },
+            AfbExitSlot::Event(uid, evtv4) => {
+                afb_log_msg!(Debug, None, "exit release event:{}", uid);
+                unsafe { cglue::afb_event_unref(evtv4) };
+            },
+            AfbExitSlot::Hook(uid, hook) => {
+                afb_log_msg!(Debug, None, "exit call hook:{}", uid);
+                if let Err(error) = afb_catch_panic(uid, hook) {
+                    afb_log_msg!(Error, None, &error);
+                }
+            },
+        }
+    }
+
+    // hooks may still use timers/evtfds, free them last (reverse registration order)
+    for slot in leaked {
+        match slot {
+            AfbExitSlot::Timer(timer) => drop(unsafe { Box::from_raw(timer) }),
+            AfbExitSlot::EvtFd(evtfd) => drop(unsafe { Box::from_raw(evtfd) }),
+            _ => {},
+        }
+    }
+}
+
+thread_local! {
+    // location of the last panic raised on this thread, set by afb panic hook
+    static AFB_PANIC_LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+static AFB_PANIC_HOOK: Once = Once::new();
+
+fn panic_hook_install() {
+    AFB_PANIC_HOOK.call_once(|| {
+        let previous = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            if let Some(location) = info.location() {
+                let location =
+                    format!("{}:{}:{}", location.file(), location.line(), location.column());
+                AFB_PANIC_LOCATION.with(|slot| *slot.borrow_mut() = Some(location));
+            }
+            previous(info);
+        }));
+    });
+}
+
+/// Run user code called from a libafb callback. A panic never unwinds into C: it is caught
+/// and returned as an `afb-panic` error (status -100) holding panic message and location.
+#[track_caller]
+pub fn afb_catch_panic<F, R>(uid: &str, callback: F) -> Result<R, AfbError>
+where
+    F: FnOnce() -> R,
+{
+    panic_hook_install();
+    match std::panic::catch_unwind(AssertUnwindSafe(callback)) {
+        Ok(result) => Ok(result),
+        Err(payload) => {
+            let message = if let Some(message) = payload.downcast_ref::<&str>() {
+                message.to_string()
+            } else if let Some(message) = payload.downcast_ref::<String>() {
+                message.clone()
+            } else {
+                "non string panic payload".to_string()
+            };
+            let location = AFB_PANIC_LOCATION
+                .with(|slot| slot.borrow_mut().take())
+                .unwrap_or_else(|| "unknown location".to_string());
+            Err(AfbError::new(
+                "afb-panic",
+                -100,
+                format!("{} panicked:'{}' at {}", uid, message, location),
+            ))
+        },
+    }
+}
@@ -330,3 +330,37 @@ fn get_from_array() {
         }
     }
 }
+
+// ------------------------------------------------
+// testing json schema validation
+// -------------------------------------------------
+#[test]
+fn validate_schema() -> Result<(), AfbError> {
+    let jschema = JsoncObj::parse(
+        "{'type':'object', 'required':['name'], 'properties':{'name':{'type':'string','minLength':2}, \
+         'level':{'type':'integer','minimum':0,'maximum':10}, 'tags':{'type':'array','items':{'enum':['a','b']}}}, \
+         'additionalProperties':false}",
+    )?;
+
+    let jvalid = JsoncObj::parse("{'name':'IoT.bzh', 'level':5, 'tags':['a','b']}")?;
+    jvalid.validate(&jschema)?;
+
+    let jlevel = JsoncObj::parse("{'name':'IoT.bzh', 'level':11}")?;
+    match jlevel.validate(&jschema) {
+        Ok(()) => panic!("level above maximum should fail"),
+        Err(error) => assert!(error.get_info().starts_with("$.level:"), "{}", error),
+    }
+
+    let jtags = JsoncObj::parse("{'name':'IoT.bzh', 'tags':['a','c']}")?;
+    match jtags.validate(&jschema) {
+        Ok(()) => panic!("tag out of enum should fail"),
+        Err(error) => assert!(error.get_info().starts_with("$.tags[1]:"), "{}", error),
+    }
+
+    let jmissing = JsoncObj::parse("{'level':1}")?;
+    assert!(jmissing.validate(&jschema).is_err(), "missing required key should fail");
+
+    let jextra = JsoncObj::parse("{'name':'IoT.bzh', 'extra':true}")?;
+    assert!(jextra.validate(&jschema).is_err(), "additional property should fail");
+    Ok(())
+}
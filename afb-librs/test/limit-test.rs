@@ -0,0 +1,44 @@
+// for test run 'clear && cargo test limit'
+// ----------------------------------------
+// start test => cargo test --lib -- --exact
+
+use crate::prelude::*;
+
+// ------------------------------------------------
+// testing verb/group admission limits
+// -------------------------------------------------
+#[test]
+fn limit_token_bucket() {
+    // 20 tokens/s, burst of 2
+    let limit = AfbLimit::new("test-rate").set_rate(20, 2);
+    let _first = limit.acquire(None).expect("first token");
+    let _second = limit.acquire(None).expect("second token");
+    match limit.acquire(None) {
+        Ok(_) => panic!("burst should be exhausted"),
+        Err(error) => assert_eq!(error.get_uid(), "limit-rate"),
+    }
+
+    // one token is back after 1/20s
+    std::thread::sleep(std::time::Duration::from_millis(60));
+    assert!(limit.acquire(None).is_ok());
+}
+
+#[test]
+fn limit_inflight_and_quota() {
+    let limit = AfbLimit::new("test-inflight").set_max_inflight(1);
+    let guard = limit.acquire(None).expect("first request");
+    assert_eq!(limit.get_inflight(), 1);
+    match limit.acquire(None) {
+        Ok(_) => panic!("max in-flight should be reached"),
+        Err(error) => assert_eq!(error.get_uid(), "limit-inflight"),
+    }
+    drop(guard);
+    assert_eq!(limit.get_inflight(), 0);
+    assert!(limit.acquire(None).is_ok());
+
+    let quota = AfbLimit::new("test-quota").set_session_quota(1);
+    assert!(quota.acquire(None).is_err(), "quota requires a session");
+    let _client1 = quota.acquire(Some("client-1")).expect("client-1 first request");
+    assert!(quota.acquire(Some("client-1")).is_err());
+    assert!(quota.acquire(Some("client-2")).is_ok());
+}
@@ -0,0 +1,46 @@
+// for test run 'clear && cargo test --features session-persist persist'
+// ----------------------------------------
+// start test => cargo test --lib -- --exact
+
+use crate::prelude::*;
+
+// ------------------------------------------------
+// testing shared session persistence
+// -------------------------------------------------
+#[derive(serde::Serialize, serde::Deserialize, PartialEq, Debug)]
+struct TestSessionPersist {
+    name: String,
+    count: u32,
+}
+
+impl AfbSessionData for TestSessionPersist {}
+
+fn session_persist_roundtrip(uuid: &str, format: AfbSessionFormat) -> Result<(), AfbError> {
+    let tmpdir = tempfile::tempdir().expect("temporary directory");
+    let path = tmpdir.path().join(uuid);
+    let persist = AfbSessionPersist::new(&path.to_string_lossy());
+    persist.set_format(format).add_type::<TestSessionPersist>("test-persist");
+
+    AfbSessionStore::insert(uuid, TestSessionPersist { name: uuid.to_string(), count: 42 });
+    persist.save()?;
+    AfbSessionStore::close(uuid);
+    assert!(AfbSessionStore::get::<TestSessionPersist>(uuid).is_none());
+
+    // snapshot is private to binder user
+    use std::os::unix::fs::PermissionsExt;
+    let mode = std::fs::metadata(persist.get_path()).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+
+    assert!(persist.restore()? >= 1);
+    let session = AfbSessionStore::get::<TestSessionPersist>(uuid).expect("session restored");
+    assert_eq!(*session.lock(), TestSessionPersist { name: uuid.to_string(), count: 42 });
+    AfbSessionStore::close(uuid);
+    Ok(())
+}
+
+// formats run one after the other, a snapshot holds every session of the store
+#[test]
+fn session_persist() -> Result<(), AfbError> {
+    session_persist_roundtrip("test-persist-json", AfbSessionFormat::Json)?;
+    session_persist_roundtrip("test-persist-cbor", AfbSessionFormat::Cbor)
+}
@@ -0,0 +1,97 @@
+// for test run 'clear && cargo test session'
+// ----------------------------------------
+// start test => cargo test --lib -- --exact
+
+use crate::prelude::*;
+
+// ------------------------------------------------
+// testing shared session idle/lifetime expiry
+// -------------------------------------------------
+#[test]
+fn session_expire_check() {
+    use std::time::{Duration, Instant};
+    let created = Instant::now();
+    let accessed = created + Duration::from_secs(5);
+    let idle = Some(Duration::from_secs(10));
+    let lifetime = Some(Duration::from_secs(60));
+
+    let check =
+        |now: Duration| AfbSessionExpire::check(created, accessed, idle, lifetime, created + now);
+    assert_eq!(check(Duration::from_secs(14)), None);
+    assert_eq!(check(Duration::from_secs(15)), Some(AfbSessionExpire::Idle));
+    assert_eq!(check(Duration::from_secs(60)), Some(AfbSessionExpire::Lifetime));
+
+    // no timeout never expires
+    let never =
+        AfbSessionExpire::check(created, accessed, None, None, created + Duration::from_secs(3600));
+    assert_eq!(never, None);
+}
+
+// ------------------------------------------------
+// testing shared session expire callbacks
+// -------------------------------------------------
+static TEST_SESSION_EXPIRED: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+
+#[test]
+fn session_on_expire() {
+    let uuid = "test-session-expire";
+    let handle = AfbSessionStore::on_expire(move |closed, reason| {
+        if closed == uuid {
+            assert_eq!(reason, AfbSessionExpire::Closed);
+            TEST_SESSION_EXPIRED.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
+    });
+    AfbSessionStore::insert(uuid, TestSessionCounter { count: 0 });
+    AfbSessionStore::close(uuid);
+    assert_eq!(TEST_SESSION_EXPIRED.load(std::sync::atomic::Ordering::Relaxed), 1);
+
+    // unregistered callback is not called anymore
+    assert!(handle.unregister());
+    assert!(!handle.unregister());
+    AfbSessionStore::insert(uuid, TestSessionCounter { count: 0 });
+    AfbSessionStore::close(uuid);
+    assert_eq!(TEST_SESSION_EXPIRED.load(std::sync::atomic::Ordering::Relaxed), 1);
+}
+
+// ------------------------------------------------
+// testing binding-wide shared session store
+// -------------------------------------------------
+struct TestSessionCounter {
+    count: u32,
+}
+
+static TEST_SESSION_CLOSED: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+
+impl AfbSessionData for TestSessionCounter {
+    fn closing(&mut self, _uuid: &str) {
+        TEST_SESSION_CLOSED.fetch_add(self.count, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn session_store() -> Result<(), AfbError> {
+    let uuid = "test-session-store";
+    assert!(AfbSessionStore::get::<TestSessionCounter>(uuid).is_none());
+
+    let session = AfbSessionStore::get_or_insert_with(uuid, || TestSessionCounter { count: 1 })?;
+    session.lock().count += 1;
+    let again = AfbSessionStore::get_or_insert_with(uuid, || TestSessionCounter { count: 100 })?;
+    assert_eq!(again.lock().count, 2);
+
+    // value in use cannot be taken
+    assert!(AfbSessionStore::take::<TestSessionCounter>(uuid).is_err());
+    drop(session);
+    drop(again);
+    let value =
+        AfbSessionStore::take::<TestSessionCounter>(uuid)?.expect("value should be defined");
+    assert_eq!(value.count, 2);
+    assert!(AfbSessionStore::take::<TestSessionCounter>(uuid)?.is_none());
+
+    // close runs value closing hook once
+    AfbSessionStore::insert(uuid, TestSessionCounter { count: 7 });
+    AfbSessionStore::close(uuid);
+    AfbSessionStore::close(uuid);
+    assert_eq!(TEST_SESSION_CLOSED.load(std::sync::atomic::Ordering::Relaxed), 7);
+    assert!(!AfbSessionStore::get_uuids().iter().any(|key| key == uuid));
+    Ok(())
+}
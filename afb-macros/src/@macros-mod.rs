@@ -0,0 +1,238 @@
+/*
+ * Copyright (C) 2015-2023 IoT.bzh Company
+ * Author: Fulup Ar Foll <fulup@iot.bzh>
+ *
+ * $RP_BEGIN_LICENSE$
+ * Commercial License Usage
+ *  Licensees holding valid commercial IoT.bzh licenses may use this file in
+ *  accordance with the commercial license agreement provided with the
+ *  Software or, alternatively, in accordance with the terms contained in
+ *  a written agreement between you and The IoT.bzh Company. For licensing terms
+ *  and conditions see https://www.iot.bzh/terms-conditions. For further
+ *  information use the contact form at https://www.iot.bzh/contact.
+ *
+ * GNU General Public License Usage
+ *  Alternatively, this file may be used under the terms of the GNU General
+ *  Public license version 3. This license is as published by the Free Software
+ *  Foundation and appearing in the file LICENSE.GPLv3 included in the packaging
+ *  of this file. Please review the following information to ensure the GNU
+ *  General Public License requirements will be met
+ *  https://www.gnu.org/licenses/gpl-3.0.html.
+ * $RP_END_LICENSE$
+ */
+
+// Procedural macros re-exported by afbv4 prelude. Generated code only uses `::afbv4` paths, the
+// afbv4 crate declares itself under that name to use them internally.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, FnArg, GenericArgument, ItemFn, MetaNameValue, PathArguments, ReturnType,
+    Token, Type,
+};
+
+/// Typed verb: `#[afb_verb(name = "...", info = "...", permission = "acl:xxx")]` on a
+/// `fn(&AfbRequest, Input) -> Result<Output, AfbError>` generates a module of the same name whose
+/// `register()` returns the `AfbVerb`. Argument is decoded through `ConvertQuery`, response encoded
+/// through `ConvertResponse`. Usage is built from Input/Output types (`USAGE`), a sample is added
+/// from `AfbVerbSample` when Input implements it and no `sample` is given.
+///
+/// Supported keys: name, info, permission, usage, sample (may be repeated), verbosity,
+/// input_schema and output_schema.
+#[proc_macro_attribute]
+pub fn afb_verb(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let args =
+        parse_macro_input!(attr with Punctuated::<MetaNameValue, Token![,]>::parse_terminated);
+    let func = parse_macro_input!(item as ItemFn);
+    match afb_verb_expand(args, func) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn afb_verb_expand(
+    args: Punctuated<MetaNameValue, Token![,]>,
+    func: ItemFn,
+) -> syn::Result<TokenStream2> {
+    let sig = &func.sig;
+    if let Some(asyncness) = &sig.asyncness {
+        return Err(syn::Error::new(
+            asyncness.span(),
+            "afb_verb expects a sync callback, use AfbVerb::set_async_callback for async verbs",
+        ));
+    }
+    let input = match (sig.inputs.len(), sig.inputs.last()) {
+        (2, Some(FnArg::Typed(input))) => &*input.ty,
+        _ => {
+            return Err(syn::Error::new(
+                sig.inputs.span(),
+                "afb_verb expects fn(&AfbRequest, Input) -> Result<Output, AfbError>",
+            ))
+        },
+    };
+    let output = result_type(&sig.output).ok_or_else(|| {
+        syn::Error::new(
+            sig.output.span(),
+            "afb_verb callback should return Result<Output, AfbError>",
+        )
+    })?;
+
+    let ident = &sig.ident;
+    let vis = &func.vis;
+    let uid = ident.to_string();
+    let mut usage = {
+        let usage = format!("input:{} output:{}", type_name(input), type_name(output));
+        quote! { #usage }
+    };
+    let mut settings = Vec::new();
+    let mut sampled = false;
+    for arg in args {
+        let value = &arg.value;
+        let key = match arg.path.get_ident() {
+            Some(key) => key.to_string(),
+            None => return Err(syn::Error::new(arg.path.span(), "afb_verb key should be a name")),
+        };
+        settings.push(match key.as_str() {
+            "name" => quote! { verb.set_name(#value); },
+            "info" => quote! { verb.set_info(#value); },
+            "permission" => {
+                quote! { verb.set_permission(::afbv4::prelude::AfbPermission::new(#value)); }
+            },
+            "usage" => {
+                usage = quote! { #value };
+                continue;
+            },
+            "sample" => {
+                sampled = true;
+                quote! { verb.add_sample(#value)?; }
+            },
+            "verbosity" => quote! { verb.set_verbosity(#value)?; },
+            "input_schema" => quote! { verb.set_input_schema(#value)?; },
+            "output_schema" => quote! { verb.set_output_schema(#value)?; },
+            _ => {
+                return Err(syn::Error::new(
+                    arg.path.span(),
+                    format!("afb_verb unsupported key '{}'", key),
+                ))
+            },
+        });
+    }
+    if !sampled {
+        let sample_type = strip_reference(input);
+        settings.push(quote! {
+            {
+                use ::afbv4::prelude::{AfbSampleProbeNone as _, AfbSampleProbeSome as _};
+                let probe = &::afbv4::prelude::AfbSampleProbe::<#sample_type>::new();
+                if let Some(sample) = probe.afb_sample() {
+                    verb.add_sample(sample.as_str())?;
+                }
+            }
+        });
+    }
+
+    Ok(quote! {
+        #func
+
+        #vis mod #ident {
+            use super::*;
+
+            pub const USAGE: &str = #usage;
+
+            #[track_caller]
+            fn verb_cb(
+                request: &::afbv4::prelude::AfbRequest,
+                args: &::afbv4::prelude::AfbRqtData,
+                _ctx: &::afbv4::prelude::AfbCtxData,
+            ) -> Result<(), ::afbv4::prelude::AfbError> {
+                // input/output types are inferred from user callback signature
+                let input = args.get(0)?;
+                let output = super::#ident(request, input)?;
+                request.reply(output, 0);
+                Ok(())
+            }
+
+            pub fn register(
+            ) -> Result<&'static ::afbv4::prelude::AfbVerb, ::afbv4::prelude::AfbError> {
+                let verb = ::afbv4::prelude::AfbVerb::new(#uid);
+                verb.set_callback(verb_cb).set_usage(USAGE);
+                #(#settings)*
+                verb.finalize()
+            }
+        }
+    })
+}
+
+// `Output` from `Result<Output, AfbError>`
+fn result_type(output: &ReturnType) -> Option<&Type> {
+    let segment = match output {
+        ReturnType::Type(_, ty) => match &**ty {
+            Type::Path(path) => path.path.segments.last()?,
+            _ => return None,
+        },
+        ReturnType::Default => return None,
+    };
+    if segment.ident != "Result" {
+        return None;
+    }
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) => match args.args.first()? {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn strip_reference(ty: &Type) -> &Type {
+    match ty {
+        Type::Reference(reference) => strip_reference(&reference.elem),
+        Type::Paren(paren) => strip_reference(&paren.elem),
+        Type::Group(group) => strip_reference(&group.elem),
+        _ => ty,
+    }
+}
+
+// type name without module path nor lifetime (afb_converter::MySimpleData -> MySimpleData)
+fn type_name(ty: &Type) -> String {
+    match ty {
+        Type::Reference(reference) => {
+            let mutability = if reference.mutability.is_some() { "mut " } else { "" };
+            format!("&{}{}", mutability, type_name(&reference.elem))
+        },
+        Type::Path(path) => match path.path.segments.last() {
+            Some(segment) => {
+                let mut name = segment.ident.to_string();
+                if let PathArguments::AngleBracketed(args) = &segment.arguments {
+                    let args: Vec<String> = args
+                        .args
+                        .iter()
+                        .filter_map(|arg| match arg {
+                            GenericArgument::Type(ty) => Some(type_name(ty)),
+                            _ => None,
+                        })
+                        .collect();
+                    if !args.is_empty() {
+                        name.push_str(&format!("<{}>", args.join(", ")));
+                    }
+                }
+                name
+            },
+            None => String::new(),
+        },
+        Type::Tuple(tuple) => {
+            let elems: Vec<String> = tuple.elems.iter().map(type_name).collect();
+            format!("({})", elems.join(", "))
+        },
+        Type::Slice(slice) => format!("[{}]", type_name(&slice.elem)),
+        Type::Array(array) => {
+            let len = &array.len;
+            format!("[{}; {}]", type_name(&array.elem), quote! { #len })
+        },
+        Type::Paren(paren) => type_name(&paren.elem),
+        Type::Group(group) => type_name(&group.elem),
+        other => quote! { #other }.to_string().replace(' ', ""),
+    }
+}
@@ -19,6 +19,7 @@ use afbv4::prelude::*;
 // automatically generate json encoder/decoder for MySimpleData
 AfbDataConverter!(simple_data, MySimpleData);
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU32, Ordering};
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct MySimpleData {
     pub name: String,
@@ -26,6 +27,19 @@ pub struct MySimpleData {
     pub y: i32,
 }
 
+// count values freed by libafb, checks subcall replies are released once consumed
+static SIMPLE_DATA_RELEASED: AtomicU32 = AtomicU32::new(0);
+
+impl Drop for MySimpleData {
+    fn drop(&mut self) {
+        SIMPLE_DATA_RELEASED.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+pub fn simple_data_released() -> u32 {
+    SIMPLE_DATA_RELEASED.load(Ordering::Relaxed)
+}
+
 pub fn register(_binding: AfbApiV4) -> Result<(), AfbError> {
     // Custom type should be registered at binding startup time
     simple_data::register()?;
@@ -5,7 +5,7 @@ export LD_LIBRARY_PATH="/usr/local/lib64:$LD_LIBRARY_PATH"
 export PATH="/usr/local/lib64:$PATH"
 clear
 
-cargo build --example afb_samples
+cargo build --example afb_samples --example afb_multi_api
 if test $? != 0; then
         echo "FATAL: fail to compile libafb sample"
         exit 1
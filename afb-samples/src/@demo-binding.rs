@@ -71,10 +71,28 @@ impl AfbApiControls for ApiUserData {
         afb_log_msg!(Notice, api, "--api-started api={}", api.get_uid());
         Ok(())
     }
+}
+
+// trace every verb entry and reply status
+struct ApiTracer {}
+impl AfbInterceptor for ApiTracer {
+    fn before(
+        &self,
+        request: &AfbRequest,
+        _args: &AfbRqtData,
+    ) -> Result<AfbInterceptStatus, AfbError> {
+        afb_log_msg!(Debug, request, "--verb-enter verb={}", request.get_verb().get_uid());
+        Ok(AfbInterceptStatus::Continue)
+    }
 
-    // mandatory for downcasting back to custom apidata object
-    fn as_any(&mut self) -> &mut dyn Any {
-        self
+    fn after(&self, request: &AfbRequest, status: i32, _reply: &AfbParams) {
+        afb_log_msg!(
+            Debug,
+            request,
+            "--verb-reply verb={} status={}",
+            request.get_verb().get_uid(),
+            status
+        );
     }
 }
 
@@ -90,6 +108,7 @@ pub fn binding_init(rootv4: AfbApiV4, jconf: JsoncObj) -> Result<&'static AfbApi
         .set_info("My first Rust API")
         .set_permission(AfbPermission::new("acl:rust"))
         .set_callback(Box::new(ApiUserData { _any_data: "skipail" }))
+        .add_interceptor(Box::new(ApiTracer {}))
         .set_verbosity(verbosity)?
         .add_verb(verb_probe::register(rootv4)?)
         .add_verb(verb_basic::register(rootv4)?)
@@ -101,10 +120,17 @@ pub fn binding_init(rootv4: AfbApiV4, jconf: JsoncObj) -> Result<&'static AfbApi
         .add_group(loa_group::register(rootv4)?)
         .add_group(session_group::register(rootv4)?)
         .add_group(subapi_group::register(rootv4)?)
-        .seal(false)
-        .finalize()?;
+        .add_apidoc_cb(true)
+        .add_stats_cb(AfbPermission::new("acl:stats"))
+        .add_verbosity_cb(AfbPermission::new("acl:verbosity"))
+        .seal(false);
+
+    // optional verb/group admission limits from binding config
+    if let Some(jlimits) = jconf.optional::<JsoncObj>("limits")? {
+        api.set_limits(&jlimits)?;
+    }
 
-    Ok(api)
+    api.finalize()
 }
 
 // register binding within libafb
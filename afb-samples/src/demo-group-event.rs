@@ -126,7 +126,9 @@ pub fn register(apiv4: AfbApiV4) -> Result<&'static AfbGroup, AfbError> {
     afb_log_msg!(Notice, apiv4, "Registering group={}", mod_name);
 
     // create event and build share Arc context data
-    let event = AfbEvent::new("demo-event").finalize()?;
+    let event = AfbEvent::new("demo-event")
+        .set_info("demo event pushed on request")
+        .finalize()?;
     let ctxdata = Arc::new(UserCtxData { counter: Cell::new(0), event });
 
     let simple_event_handler = AfbEvtHandler::new("handler-1")
@@ -137,6 +139,7 @@ pub fn register(apiv4: AfbApiV4) -> Result<&'static AfbGroup, AfbError> {
         .finalize()?;
 
     let unsubscribe = AfbVerb::new("unsubscribe")
+        .add_alias("unsub")
         .set_callback(unsubscribe_callback)
         .set_context(EvtUserData { ctx: Arc::clone(&ctxdata) })
         .set_info("unsubscribe to event")
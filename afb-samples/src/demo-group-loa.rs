@@ -35,13 +35,10 @@ fn reset_loa_cb(
     Ok(())
 }
 
-fn check_loa_cb(
-    request: &AfbRequest,
-    _args: &AfbRqtData,
-    _ctx: &AfbCtxData,
-) -> Result<(), AfbError> {
-    request.reply("Protected API with LOA>=1 OK", 0);
-    Ok(())
+// fine grain check on top of verb static LOA permission, async as authority may be remote
+async fn check_loa_cb(request: AfbRequest, _args: AfbRqtData) -> Result<String, AfbError> {
+    let admin = request.has_permission_async("acl:loa:admin").await?;
+    Ok(format!("Protected API with LOA={} OK (admin:{})", request.get_loa(), admin))
 }
 
 // prefix group of event verbs and attach a default privilege
@@ -64,7 +61,7 @@ pub fn register(apiv4: AfbApiV4) -> Result<&'static AfbGroup, AfbError> {
         .finalize()?;
 
     let check = AfbVerb::new("check")
-        .set_callback(check_loa_cb)
+        .set_async_callback(check_loa_cb)
         .set_info("Request LOA>=1 to accept incoming request")
         .set_usage("no input")
         .set_permission(AfbPermission::new(1))
@@ -19,6 +19,26 @@ fn session_drop_cb(session: &mut SessionUserData) {
     afb_log_msg!(Debug, None, "session closing count={}", session.count);
 }
 
+// typed session value, lives next to SessionUserData without any registration
+#[derive(Default)]
+struct SessionVisits {
+    count: u32,
+}
+
+fn visits_callback(
+    request: &AfbRequest,
+    _args: &AfbRqtData,
+    _ctx: &AfbCtxData,
+) -> Result<(), AfbError> {
+    let visits = request.session_or_insert_with(SessionVisits::default)?;
+    let count = visits.with(|visits| {
+        visits.count += 1;
+        visits.count
+    });
+    request.reply(count, 0);
+    Ok(())
+}
+
 fn create_callback(
     request: &AfbRequest,
     _args: &AfbRqtData,
@@ -75,6 +95,12 @@ pub fn register(apiv4: AfbApiV4) -> Result<&'static AfbGroup, AfbError> {
         .set_callback(get_callback)
         .finalize()?;
 
+    let visits = AfbVerb::new("visits")
+        .set_info("count client requests with a typed session value")
+        .set_usage("no input")
+        .set_callback(visits_callback)
+        .finalize()?;
+
     let group = AfbGroup::new(mod_name)
         .set_info("session demo group")
         .set_prefix(mod_name)
@@ -83,6 +109,7 @@ pub fn register(apiv4: AfbApiV4) -> Result<&'static AfbGroup, AfbError> {
         .add_verb(create)
         .add_verb(drop)
         .add_verb(read)
+        .add_verb(visits)
         .finalize()?;
     Ok(group)
 }
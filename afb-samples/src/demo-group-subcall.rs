@@ -7,6 +7,7 @@
  */
 
 // import libafb dependencies
+use afb_converter::{simple_data_released, MySimpleData};
 use afbv4::prelude::*;
 
 // subcall demo create a dummy "loop-test/ping" to enable loopback test
@@ -87,12 +88,50 @@ fn sync_call_verb(
     Ok(())
 }
 
+// async verb awaiting subcall responses without intermediate callback
+async fn await_call_verb(request: AfbRequest, _args: AfbRqtData) -> Result<AfbRqtData, AfbError> {
+    let first = AfbSubCall::call(&request, "loop-test", "ping", AFB_NO_DATA);
+    let second = AfbSubCall::call(&request, "loop-test", "ping", AFB_NO_DATA);
+    first.await?;
+    let response = second.await?;
+    Ok(response)
+}
+
+fn simple_data_verb(
+    request: &AfbRequest,
+    _args: &AfbRqtData,
+    _ctx: &AfbCtxData,
+) -> Result<(), AfbError> {
+    request.reply(MySimpleData { name: "loop-test".to_owned(), x: 1, y: 2 }, 0);
+    Ok(())
+}
+
+// awaited response holds its own data reference, dropping it should free the reply value
+async fn await_release_verb(request: AfbRequest, _args: AfbRqtData) -> Result<u32, AfbError> {
+    let response = AfbSubCall::call(&request, "loop-test", "simple-data", AFB_NO_DATA).await?;
+    let before = simple_data_released();
+    if response.get::<&MySimpleData>(0)?.name != "loop-test" {
+        return afb_error!("await-release", "unexpected simple-data response");
+    }
+    drop(response);
+    let released = simple_data_released() - before;
+    if released != 1 {
+        return afb_error!("await-release", "subcall reply released {} time(s)", released);
+    }
+    Ok(released)
+}
+
 pub fn register(apiv4: AfbApiV4) -> Result<&'static AfbGroup, AfbError> {
     // build verb name from Rust module name
     let mod_name = module_path!().split(':').next_back().unwrap();
     afb_log_msg!(Notice, apiv4, "Registering group={}", mod_name);
 
-    match AfbApi::new("loop-test").finalize() {
+    let simple_data = AfbVerb::new("simple-data")
+        .set_callback(simple_data_verb)
+        .set_info("reply a MySimpleData typed value")
+        .finalize()?;
+
+    match AfbApi::new("loop-test").add_verb(simple_data).finalize() {
         Ok(api_test) => {
             afb_log_msg!(Notice, apiv4, "Loopback api uid={} started", api_test.get_uid());
         },
@@ -114,6 +153,18 @@ pub fn register(apiv4: AfbApiV4) -> Result<&'static AfbGroup, AfbError> {
         .set_usage("no input")
         .finalize()?;
 
+    let await_call = AfbVerb::new("await-call")
+        .set_async_callback(await_call_verb)
+        .set_info("async/await calls to loop-test/ping")
+        .set_usage("no input")
+        .finalize()?;
+
+    let await_release = AfbVerb::new("await-release")
+        .set_async_callback(await_release_verb)
+        .set_info("check awaited subcall reply is released once dropped")
+        .set_usage("no input")
+        .finalize()?;
+
     let group = AfbGroup::new(mod_name)
         .set_info("timer demo api group")
         .set_prefix(mod_name)
@@ -121,6 +172,8 @@ pub fn register(apiv4: AfbApiV4) -> Result<&'static AfbGroup, AfbError> {
         .set_verbosity(3)?
         .add_verb(sync_call)
         .add_verb(async_call)
+        .add_verb(await_call)
+        .add_verb(await_release)
         .finalize()?;
 
     Ok(group)
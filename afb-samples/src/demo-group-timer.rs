@@ -10,6 +10,8 @@
 use afbv4::prelude::*;
 use std::cell::Cell;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
 
 //lib afb support two class of timers
 // - schedjob is delay+watchdog it starts a callback after a delay(ms) and limit exec time(seconds).
@@ -147,6 +149,15 @@ fn jobpost_verb(request: &AfbRequest, args: &AfbRqtData, ctx: &AfbCtxData) -> Re
     Ok(())
 }
 
+// async verb, the future is polled from libafb job loop and its returned value is the response
+async fn async_delay_verb(request: AfbRequest, args: AfbRqtData) -> Result<JsoncObj, AfbError> {
+    // json objects are not Send, only extract them once awaits are done
+    AfbDelay::new(1000).await?;
+    let jquery = args.get::<JsoncObj>(0)?;
+    afb_log_msg!(Info, &request, "async delay done query={}", jquery);
+    Ok(jquery)
+}
+
 // prefix group of event verbs and attach a default privilege
 pub fn register(apiv4: AfbApiV4) -> Result<&'static AfbGroup, AfbError> {
     // build verb name from Rust module name
@@ -176,6 +187,24 @@ pub fn register(apiv4: AfbApiV4) -> Result<&'static AfbGroup, AfbError> {
         .set_usage("no input")
         .finalize()?;
 
+    let async_verb = AfbVerb::new("async-delay")
+        .set_async_callback(async_delay_verb)
+        .set_info("return query after 1s")
+        .set_usage("any json")
+        .finalize()?;
+
+    // closure callback, captured counter is typed and does not need AfbCtxData
+    let tic_count = Arc::new(AtomicU32::new(0));
+    let tic_verb = AfbVerb::new("tic-count")
+        .set_callback(move |request, _args, _ctx| {
+            let count = tic_count.fetch_add(1, Ordering::Relaxed) + 1;
+            request.reply(count, 0);
+            Ok(())
+        })
+        .set_info("closure verb counting its calls")
+        .set_usage("no input")
+        .finalize()?;
+
     let group = AfbGroup::new(mod_name)
         .set_info("timer demo api group")
         .set_prefix(mod_name)
@@ -183,6 +212,8 @@ pub fn register(apiv4: AfbApiV4) -> Result<&'static AfbGroup, AfbError> {
         .set_verbosity(3)?
         .add_verb(start_timer)
         .add_verb(job_verb)
+        .add_verb(async_verb)
+        .add_verb(tic_verb)
         .add_event(event)
         .finalize()?;
 
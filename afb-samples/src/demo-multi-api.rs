@@ -0,0 +1,64 @@
+/*
+ * Copyright (C) 2015-2023 IoT.bzh Company
+ * Author: Fulup Ar Foll <fulup@iot.bzh>
+ *
+ * Redpesk samples code/config use MIT License and can be freely copy/modified even within proprietary code
+ * License: $RP_BEGIN_LICENSE$ SPDX:MIT https://opensource.org/licenses/MIT $RP_END_LICENSE$
+*/
+
+// one binding exposing two apis sharing a binding level context
+use afbv4::prelude::*;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+struct BindingCtx {
+    counter: AtomicU32,
+}
+
+// backend counts every request it receives through the binding context
+fn backend_cb(request: &AfbRequest, _args: &AfbRqtData, _ctx: &AfbCtxData) -> Result<(), AfbError> {
+    let ctx = request.get_binding()?.get_context().get_ref::<BindingCtx>()?;
+    let count = ctx.counter.fetch_add(1, Ordering::Relaxed) + 1;
+    request.reply(count, 0);
+    Ok(())
+}
+
+// frontend forwards to its sibling backend api, created first by AfbBinding::finalize
+fn frontend_cb(
+    request: &AfbRequest,
+    _args: &AfbRqtData,
+    _ctx: &AfbCtxData,
+) -> Result<(), AfbError> {
+    let response = AfbSubCall::call_sync(request, "demo-backend", "count", AFB_NO_DATA)?;
+    let count = response.get::<u32>(0)?;
+    afb_log_msg!(Notice, request, "frontend backend count={}", count);
+    request.reply(count, 0);
+    Ok(())
+}
+
+pub fn binding_init(rootv4: AfbApiV4, jconf: JsoncObj) -> Result<&'static AfbBinding, AfbError> {
+    afb_log_msg!(Notice, rootv4, "-- multi-api binding config={}", jconf);
+
+    let backend_verb = AfbVerb::new("count")
+        .set_callback(backend_cb)
+        .set_info("increment and return binding counter")
+        .finalize()?;
+    let frontend_verb = AfbVerb::new("count")
+        .set_callback(frontend_cb)
+        .set_info("return backend counter through a subcall")
+        .finalize()?;
+
+    let backend = AfbApi::new("demo-backend").add_verb(backend_verb);
+    let frontend = AfbApi::new("demo-frontend").require_api("demo-backend").add_verb(frontend_verb);
+
+    // frontend is added first, require_api makes backend be created before it
+    let binding = AfbBinding::new("demo-multi-api")
+        .set_info("two apis sharing one binding context")
+        .set_context(BindingCtx { counter: AtomicU32::new(0) })
+        .add_api(frontend)
+        .add_api(backend)
+        .finalize()?;
+    Ok(binding)
+}
+
+// register binding within libafb
+AfbBindingRegister!(binding_init);
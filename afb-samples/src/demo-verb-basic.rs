@@ -47,8 +47,11 @@ pub fn register(apiv4: AfbApiV4) -> Result<&'static AfbVerb, AfbError> {
         .set_callback(verb_cb)
         .set_context(MyCtxCb { _text: "toto", count: 0 })
         .set_info("My 1st demo verb")
-        .set_usage("any json string")
+        .set_usage("json object with string values")
         .add_sample("{'skipail': 'IoT.bzh', 'location':'Lorient'}")?
+        .set_input_schema(
+            "{'type':'object', 'required':['skipail'], 'additionalProperties':{'type':'string'}}",
+        )?
         .finalize()?;
     Ok(verb)
 }
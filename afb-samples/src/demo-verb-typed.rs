@@ -12,30 +12,18 @@ use afbv4::prelude::*;
 // mySimpleData type is within an external crate to allow sharing with other crate/binding/binder
 use afb_converter::*;
 
-fn typed_callback(
-    request: &AfbRequest,
-    args: &AfbRqtData,
-    _ctx: &AfbCtxData,
-) -> Result<(), AfbError> {
-    // check arg0 match MySimpleData grammar
-    let input = args.get::<&MySimpleData>(0)?;
+// input is decoded with ConvertQuery and output encoded with ConvertResponse by #[afb_verb]
+#[afb_verb(
+    name = "verb_typed",
+    info = "My 2nd demo verb",
+    sample = "{'x': 1, 'y':99, 'name':'IoT.bzh'}"
+)]
+fn typed_callback(request: &AfbRequest, input: &MySimpleData) -> Result<MySimpleData, AfbError> {
+    afb_log_msg!(Debug, request, "got simple-data={:?}", input);
 
     // create a sample simple-data object as response
     let output = MySimpleData { name: input.name.to_uppercase(), x: input.x + 1, y: input.y - 1 };
-
-    // closure is call from following 'if let' with reply()
-    let reply = || -> Result<(), AfbError> {
-        let mut response = AfbParams::new();
-        response.push(output)?;
-        request.reply(response, 0);
-        Ok(())
-    };
-
-    // if data export fail send an error report
-    if let Err(error) = reply() {
-        request.reply(afb_add_trace!(error), 405);
-    }
-    Ok(())
+    Ok(output)
 }
 
 pub fn register(rootv4: AfbApiV4) -> Result<&'static AfbVerb, AfbError> {
@@ -46,12 +34,5 @@ pub fn register(rootv4: AfbApiV4) -> Result<&'static AfbVerb, AfbError> {
     let mod_name = module_path!().split(':').next_back().unwrap();
     afb_log_msg!(Notice, rootv4, "Registering verb={}", mod_name);
 
-    let group = AfbVerb::new(mod_name)
-        .set_callback(typed_callback)
-        .set_info("My 2nd demo verb")
-        .set_usage("any json string")
-        .add_sample("{'x': 1, 'y':99, 'name':'IoT.bzh'}")?
-        .finalize()?;
-
-    Ok(group)
+    typed_callback::register()
 }
@@ -97,6 +97,11 @@ impl AfbApiControls for TapUserData {
             .set_onsuccess("check-session")
             .finalize()?;
 
+        let test6 = AfbTapTest::new("await-release", "rust-api", "subcall_group/await-release")
+            .set_info("Check awaited subcall reply is released once dropped")
+            .add_expect(1)?
+            .finalize()?;
+
         // ------ SESSION Group -----------
         let rqt1 = AfbTapTest::new("session-check1", "rust-api", "session_group/reset")
             .set_info("Create a new session")
@@ -216,6 +221,7 @@ impl AfbApiControls for TapUserData {
             .add_test(test3)
             .add_test(test4)
             .add_test(test5)
+            .add_test(test6)
             .add_group(session_group)
             .add_group(event_group)
             .add_group(loa_group)
@@ -254,11 +260,6 @@ impl AfbApiControls for TapUserData {
 
         Ok(())
     }
-
-    // mandatory for downcasting back to custom apidata object
-    fn as_any(&mut self) -> &mut dyn Any {
-        self
-    }
 }
 
 // rootv4 init callback started at rootv4 load time before any API exist